@@ -0,0 +1,200 @@
+
+use crate::cache::fnv1a;
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// Directory which assets are downloaded into, relative to the exported
+/// files.
+pub const ASSETS_DIR: &'static str = "assets";
+
+/// Prefixes of the URLs which github serves issue attachments from.
+const ATTACHMENT_PREFIXES: &'static [&'static str] = &[
+    "user-images.githubusercontent.com/",
+    "private-user-images.githubusercontent.com/",
+    "objects.githubusercontent.com/",
+    "github.com/user-attachments/",
+];
+
+/// Maximum length of the original file name kept in an asset's path.
+const MAX_NAME_LEN: usize = 64;
+
+/// Find the URLs of images and attachments in rendered markdown or HTML,
+/// without duplicates, in order of appearance.
+///
+/// These are the sources of markdown images and HTML `src` attributes,
+/// and any link to github's attachment hosts.
+pub fn find_asset_urls(doc: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for (start, end) in asset_url_spans(doc) {
+        let url = decode_entities(&doc[start..end]);
+        if !urls.iter().any(|seen| *seen == url) {
+            urls.push(url.into_owned());
+        }
+    }
+    urls
+}
+
+/// Rewrite the image and attachment URLs in rendered markdown or HTML,
+/// which `resolve` gives a replacement for.
+///
+/// As with `find_asset_urls`, `resolve` is given URLs with HTML entities
+/// decoded.
+pub fn rewrite_asset_urls<F>(doc: &str, resolve: F) -> String
+    where
+        F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(doc.len());
+    let mut copied = 0;
+    for (start, end) in asset_url_spans(doc) {
+        if let Some(replacement) = resolve(&decode_entities(&doc[start..end])) {
+            out.push_str(&doc[copied..start]);
+            out.push_str(&replacement);
+            copied = end;
+        }
+    }
+    out.push_str(&doc[copied..]);
+    out
+}
+
+/// Path to download an asset to, relative to the exported files.
+///
+/// Named by a hash of the URL, to tell apart assets of the same name,
+/// followed by the original file name. The query is left out of the hash,
+/// as github signs the URLs of private images with short-lived tokens.
+pub fn asset_path(url: &str) -> PathBuf {
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or(url);
+    let name: String = path.rsplit('/').next().unwrap_or("")
+        .chars()
+        .filter(|&c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+        .take(MAX_NAME_LEN)
+        .collect();
+    let name = name.trim_start_matches('.');
+    let name = if name.is_empty() { "asset" } else { name };
+    Path::new(ASSETS_DIR).join(format!("{:016x}-{}", fnv1a(path.as_bytes()), name))
+}
+
+/// Decode the HTML entities in a URL, such as the `&amp;` which query
+/// strings are escaped with in HTML.
+fn decode_entities(url: &str) -> Cow<str> {
+    if !url.contains('&') {
+        return Cow::Borrowed(url);
+    }
+    let mut out = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let decoded = rest.find(';')
+            .and_then(|end| {
+                let c = match &rest[1..end] {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    entity if entity.starts_with("#x") || entity.starts_with("#X") => {
+                        u32::from_str_radix(&entity[2..], 16).ok()
+                            .and_then(::std::char::from_u32)
+                    }
+                    entity if entity.starts_with('#') => entity[1..].parse().ok()
+                        .and_then(::std::char::from_u32),
+                    _ => None,
+                };
+                c.map(|c| (c, end))
+            });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Byte ranges of the image and attachment URLs in a document.
+fn asset_url_spans(doc: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut from = 0;
+    while let Some(found) = doc[from..].find("http") {
+        let start = from + found;
+        let rest = &doc[start..];
+        let after_scheme = if rest.starts_with("https://") {
+            &rest[8..]
+        } else if rest.starts_with("http://") {
+            &rest[7..]
+        } else {
+            from = start + 4;
+            continue;
+        };
+
+        let len = rest.find(|c: char| c.is_whitespace() || "()[]<>\"'`".contains(c))
+            .unwrap_or(rest.len());
+        let len = rest[..len].trim_end_matches(|c: char| ".,;:!?".contains(c)).len();
+        let end = start + len;
+
+        let before = &doc[..start];
+        let is_image = before.ends_with("src=\"")
+            || before.ends_with("src='")
+            || (before.ends_with("](") && is_md_image(&before[..before.len() - 2]));
+        let is_attachment = ATTACHMENT_PREFIXES.iter()
+            .any(|prefix| after_scheme.starts_with(prefix));
+        if is_image || is_attachment {
+            spans.push((start, end));
+        }
+        from = end.max(start + 4);
+    }
+    spans
+}
+
+/// Whether markdown ending in the text of a link is an image, which is
+/// when the link text begins with `![`.
+fn is_md_image(before_link_end: &str) -> bool {
+    let line = before_link_end.rsplit('\n').next().unwrap_or("");
+    match line.rfind('[') {
+        Some(i) => line[..i].ends_with('!'),
+        None => false,
+    }
+}
+
+#[test]
+fn asset_urls() {
+    let doc = "![shot](https://example.com/a.png) [page](https://example.com/) \
+        <img src=\"https://example.com/b.gif\"> \
+        see https://github.com/user-attachments/files/1/log.txt. \
+        ![shot](https://example.com/a.png)";
+    assert_eq!(find_asset_urls(doc), vec![
+        "https://example.com/a.png",
+        "https://example.com/b.gif",
+        "https://github.com/user-attachments/files/1/log.txt",
+    ]);
+    assert_eq!(
+        rewrite_asset_urls("![x](http://a/1.png) ![y](http://a/2.png)", |url| {
+            if url.ends_with("1.png") { Some("assets/1.png".to_owned()) } else { None }
+        }),
+        "![x](assets/1.png) ![y](http://a/2.png)",
+    );
+    assert_eq!(
+        asset_path("https://example.com/x/My%20Shot.png?jwt=1"),
+        Path::new(ASSETS_DIR).join(format!(
+            "{:016x}-My20Shot.png",
+            fnv1a(b"https://example.com/x/My%20Shot.png"),
+        )),
+    );
+    assert_eq!(
+        asset_path("https://example.com/a.png?jwt=1"),
+        asset_path("https://example.com/a.png?jwt=2"),
+    );
+    assert_eq!(
+        find_asset_urls("<img src=\"https://example.com/a.png?x=1&amp;y=2\">"),
+        vec!["https://example.com/a.png?x=1&y=2"],
+    );
+}
@@ -0,0 +1,191 @@
+
+use crate::{
+    Github,
+    TokioCore,
+    DEFAULT_PER_PAGE,
+    DEFAULT_CONCURRENCY,
+    error::*,
+    auth::GithubAuth,
+    rate_limit::RetryPolicy,
+    cache::HttpCache,
+};
+
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use hyper::{
+    Uri,
+    header::{
+        Authorization,
+        UserAgent,
+    },
+};
+use tokio_core::reactor::Handle;
+
+/// Base URL of the public github API.
+pub const GITHUB_API_ENDPOINT: &'static str = "https://api.github.com";
+
+/// User agent sent by default, resolved at compile time.
+const DEFAULT_USER_AGENT: &'static str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+);
+
+/// Builder for the github access service.
+///
+/// Defaults to the public github API, without authentication.
+#[derive(Clone)]
+pub struct GithubBuilder {
+    base_url: String,
+    user_agent: UserAgent,
+    auth: Option<GithubAuth>,
+    per_page: u32,
+    concurrency: usize,
+    dns_threads: usize,
+    retry_policy: RetryPolicy,
+    cache: Option<HttpCache>,
+}
+
+impl GithubBuilder {
+    pub fn new() -> Self {
+        GithubBuilder {
+            base_url: GITHUB_API_ENDPOINT.to_owned(),
+            user_agent: UserAgent::new(DEFAULT_USER_AGENT),
+            auth: None,
+            per_page: DEFAULT_PER_PAGE,
+            concurrency: DEFAULT_CONCURRENCY,
+            dns_threads: 4,
+            retry_policy: RetryPolicy::default(),
+            cache: None,
+        }
+    }
+
+    /// Base URL of the API, such as `https://github.example.com/api/v3`
+    /// for github enterprise, or `http://localhost:8080` for a local
+    /// stand-in server.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        let mut base_url = base_url.into();
+        while base_url.ends_with('/') {
+            base_url.pop();
+        }
+        self.base_url = base_url;
+        self
+    }
+
+    /// User agent header to send with every request.
+    pub fn user_agent(mut self, user_agent: UserAgent) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// Auth token to send with every request.
+    pub fn auth<A: Into<GithubAuth>>(mut self, auth: A) -> Self {
+        self.auth = Some(auth.into());
+        self
+    }
+
+    /// Number of elements to request per page when following paginated
+    /// resources.
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = per_page;
+        self
+    }
+
+    /// Maximum number of requests in flight when fanning out over many
+    /// issues, such as when fetching their comments.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Number of threads used for DNS resolution.
+    pub fn dns_threads(mut self, dns_threads: usize) -> Self {
+        self.dns_threads = dns_threads;
+        self
+    }
+
+    /// Policy for waiting on and retrying rate limited requests.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Directory in which to cache responses, to be revalidated with
+    /// conditional requests.
+    pub fn cache_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.cache = Some(HttpCache::new(dir));
+        self
+    }
+
+    /// Build the service on an existing tokio reactor.
+    ///
+    /// Fails if the base URL is not an absolute http or https URL.
+    pub fn build(self, handle: &Handle) -> Result<Github> {
+        validate_base_url(&self.base_url)?;
+
+        // the https connector also handles plain http URLs
+        let client = hyper::Client::configure()
+            .connector(hyper_tls::HttpsConnector::new(self.dns_threads, handle)?)
+            .build(handle);
+        Ok(Github {
+            client: Arc::new(client),
+            handle: handle.clone(),
+            base_url: Arc::new(self.base_url),
+            user_agent: self.user_agent,
+            token: self.auth
+                .map(|auth| Authorization(format!("token {}", auth.token))),
+            per_page: self.per_page,
+            concurrency: self.concurrency,
+            retry_policy: self.retry_policy,
+            rate_limit: Arc::new(Mutex::new(None)),
+            cache: self.cache,
+        })
+    }
+
+    /// Build the service, creating and returning a tokio core.
+    pub fn build_with_core(self) -> Result<(Github, TokioCore)> {
+        let tokio_core = TokioCore::new()?;
+        self.build(&tokio_core.handle())
+            .map(move |github| (github, tokio_core))
+    }
+}
+
+/// Check that a base URL is an absolute http or https URL, without a
+/// query, to which API paths can be appended.
+fn validate_base_url(base_url: &str) -> Result<()> {
+    let invalid = |reason: &str| -> Error {
+        format!("invalid API base URL {:?}: {}", base_url, reason).into()
+    };
+    let uri = Uri::from_str(base_url).map_err(|e| invalid(&e.to_string()))?;
+    match uri.scheme() {
+        Some("http") | Some("https") => (),
+        _ => return Err(invalid("expected an http or https URL")),
+    }
+    if uri.authority().map(str::is_empty).unwrap_or(true) {
+        return Err(invalid("expected a host"));
+    }
+    if uri.query().is_some() {
+        return Err(invalid("expected no query"));
+    }
+    Ok(())
+}
+
+impl Default for GithubBuilder {
+    fn default() -> Self {
+        GithubBuilder::new()
+    }
+}
+
+#[test]
+fn base_url_validation() {
+    assert!(validate_base_url(GITHUB_API_ENDPOINT).is_ok());
+    assert!(validate_base_url("http://localhost:8080").is_ok());
+    assert!(validate_base_url("https://github.example.com/api/v3").is_ok());
+    for invalid in &["github.example.com", "ftp://example.com", "https://", "not a url", "https://example.com?x=1"] {
+        assert!(validate_base_url(invalid).is_err(), "{} validated", invalid);
+    }
+}
@@ -0,0 +1,152 @@
+
+use crate::error::*;
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    str,
+};
+
+use hyper::{
+    Chunk,
+    Headers,
+    StatusCode,
+};
+
+/// On-disk cache of github responses, keyed by URL.
+///
+/// Responses are revalidated with `If-None-Match` and `If-Modified-Since`,
+/// and replayed when github responds with `304 Not Modified`, which does
+/// not count against the rate limit.
+#[derive(Clone, Debug)]
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+/// A cached response, with the validators needed to revalidate it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Raw `Link` header, needed to follow pagination on replay.
+    pub link: Option<String>,
+    pub body: String,
+}
+
+impl HttpCache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        HttpCache {
+            dir: dir.into(),
+        }
+    }
+
+    /// Look up the cached response for a URL.
+    ///
+    /// Unreadable or corrupt entries are treated as missing.
+    pub fn load(&self, url: &str) -> Option<CacheEntry> {
+        let contents = fs::read(self.entry_path(url)).ok()?;
+        let entry: CacheEntry = ::serde_json::from_slice(&contents).ok()?;
+        if entry.url == url {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Store a successful response, if it carries any validators.
+    pub fn store(&self, url: &str, headers: &Headers, body: &Chunk) -> Result<()> {
+        let entry = CacheEntry {
+            url: url.to_owned(),
+            etag: raw_header(headers, "ETag"),
+            last_modified: raw_header(headers, "Last-Modified"),
+            link: raw_header(headers, "Link"),
+            body: String::from(str::from_utf8(body)?),
+        };
+        if entry.etag.is_none() && entry.last_modified.is_none() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let mut f = File::create(self.entry_path(url))?;
+        f.write_all(&::serde_json::to_vec(&entry)?)?;
+        Ok(())
+    }
+
+    /// Cache file path for a URL.
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(url.as_bytes())))
+    }
+}
+
+impl CacheEntry {
+    /// Set the conditional request headers to revalidate this entry.
+    pub fn set_validators(&self, headers: &mut Headers) {
+        if let Some(ref etag) = self.etag {
+            headers.set_raw("If-None-Match", etag.clone());
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            headers.set_raw("If-Modified-Since", last_modified.clone());
+        }
+    }
+
+    /// Replay this entry in place of a `304 Not Modified` response.
+    pub fn replay(self, mut headers: Headers) -> (StatusCode, Headers, Chunk) {
+        if let Some(link) = self.link {
+            headers.set_raw("Link", link);
+        }
+        (StatusCode::Ok, headers, Chunk::from(self.body))
+    }
+}
+
+/// Single-valued header as a string.
+fn raw_header(headers: &Headers, name: &str) -> Option<String> {
+    let raw = headers.get_raw(name)?.one()?;
+    str::from_utf8(raw).ok().map(String::from)
+}
+
+/// FNV-1a hash, which unlike the std hasher is stable between builds.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[test]
+fn cache_round_trip() {
+    let dir = ::std::env::temp_dir()
+        .join(format!("github-issues-export-cache-test-{}", ::std::process::id()));
+    let cache = HttpCache::new(&dir);
+    let url = "https://api.github.com/repos/o/r/issues?per_page=100";
+
+    let mut headers = Headers::new();
+    headers.set_raw("ETag", "\"abc\"");
+    headers.set_raw("Last-Modified", "Tue, 01 Jan 2019 00:00:00 GMT");
+    headers.set_raw("Link", "<https://api.github.com/x?page=2>; rel=\"next\"");
+    cache.store(url, &headers, &Chunk::from("[]")).unwrap();
+
+    let entry = cache.load(url).unwrap();
+    let mut request_headers = Headers::new();
+    entry.set_validators(&mut request_headers);
+    assert_eq!(raw_header(&request_headers, "If-None-Match").unwrap(), "\"abc\"");
+    assert_eq!(
+        raw_header(&request_headers, "If-Modified-Since").unwrap(),
+        "Tue, 01 Jan 2019 00:00:00 GMT",
+    );
+
+    let (status, replayed, body) = entry.replay(Headers::new());
+    assert_eq!(status, StatusCode::Ok);
+    assert_eq!(
+        raw_header(&replayed, "Link").unwrap(),
+        "<https://api.github.com/x?page=2>; rel=\"next\"",
+    );
+    assert_eq!(&*body, b"[]");
+
+    // an entry stored under a colliding hash is for another URL
+    let other = "https://api.github.com/repos/o/other/issues";
+    fs::rename(cache.entry_path(url), cache.entry_path(other)).unwrap();
+    assert!(cache.load(other).is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,194 @@
+
+use crate::{
+    model,
+    error::*,
+};
+
+use std::{
+    io::Write,
+    str::FromStr,
+};
+
+use csv;
+
+/// Default name of the CSV file which issues are exported into.
+pub const CSV_FILE_NAME: &'static str = "issues.csv";
+
+/// Format of dates in CSV cells, in UTC, which spreadsheets recognize.
+const CSV_DATE_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S";
+
+/// Columns of issue metadata, for CSV export.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum CsvColumn {
+    Number,
+    Title,
+    State,
+    /// Login of the user who opened the issue.
+    Author,
+    /// Label names, joined by `, `.
+    Labels,
+    /// Logins of the assignees, joined by `, `.
+    Assignee,
+    /// Number of comments.
+    Comments,
+    Created,
+    /// Empty for open issues.
+    Closed,
+    Url,
+}
+
+impl CsvColumn {
+    /// Every column, in the default order.
+    pub const ALL: &'static [CsvColumn] = &[
+        CsvColumn::Number,
+        CsvColumn::Title,
+        CsvColumn::State,
+        CsvColumn::Author,
+        CsvColumn::Labels,
+        CsvColumn::Assignee,
+        CsvColumn::Comments,
+        CsvColumn::Created,
+        CsvColumn::Closed,
+        CsvColumn::Url,
+    ];
+
+    /// Name of the column, used as its header.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CsvColumn::Number => "number",
+            CsvColumn::Title => "title",
+            CsvColumn::State => "state",
+            CsvColumn::Author => "author",
+            CsvColumn::Labels => "labels",
+            CsvColumn::Assignee => "assignee",
+            CsvColumn::Comments => "comments",
+            CsvColumn::Created => "created",
+            CsvColumn::Closed => "closed",
+            CsvColumn::Url => "url",
+        }
+    }
+
+    /// Parse a comma-separated list of column names.
+    pub fn parse_list(s: &str) -> Result<Vec<CsvColumn>> {
+        s.split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Value of this column for an issue.
+    fn value(&self, issue: &model::Issue) -> String {
+        match *self {
+            CsvColumn::Number => issue.number.to_string(),
+            CsvColumn::Title => issue.title.clone(),
+            CsvColumn::State => issue.state.clone(),
+            CsvColumn::Author => issue.user.login.clone(),
+            CsvColumn::Labels => issue.labels.iter()
+                .map(|label| label.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            CsvColumn::Assignee => if issue.assignees.is_empty() {
+                issue.assignee.as_ref()
+                    .map(|user| user.login.clone())
+                    .unwrap_or_default()
+            } else {
+                issue.assignees.iter()
+                    .map(|user| user.login.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+            CsvColumn::Comments => issue.comments.to_string(),
+            CsvColumn::Created => issue.created_at.format(CSV_DATE_FORMAT).to_string(),
+            CsvColumn::Closed => issue.closed_at
+                .map(|closed_at| closed_at.format(CSV_DATE_FORMAT).to_string())
+                .unwrap_or_default(),
+            CsvColumn::Url => issue.html_url.clone(),
+        }
+    }
+}
+
+impl FromStr for CsvColumn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        CsvColumn::ALL.iter()
+            .cloned()
+            .find(|column| column.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = CsvColumn::ALL.iter()
+                    .map(|column| column.name())
+                    .collect();
+                format!(
+                    "unknown CSV column {:?}, expected one of: {}",
+                    s,
+                    names.join(", "),
+                ).into()
+            })
+    }
+}
+
+/// Writer of issue metadata as CSV, one row per issue, after a header
+/// row.
+#[derive(Clone, Debug)]
+pub struct CsvWriter {
+    columns: Vec<CsvColumn>,
+}
+
+impl CsvWriter {
+    /// Construct, with every column.
+    pub fn new() -> Self {
+        CsvWriter {
+            columns: CsvColumn::ALL.to_vec(),
+        }
+    }
+
+    /// Only write these columns, in this order.
+    pub fn with_columns<I>(mut self, columns: I) -> Self
+        where
+            I: IntoIterator<Item=CsvColumn>,
+    {
+        self.columns = columns.into_iter().collect();
+        self
+    }
+
+    /// Write the issues as CSV.
+    pub fn write<'a, I, W>(&self, issues: I, w: W) -> Result<()>
+        where
+            I: IntoIterator<Item=&'a model::Issue>,
+            W: Write,
+    {
+        let mut writer = csv::Writer::from_writer(w);
+        writer.write_record(self.columns.iter().map(|column| column.name()))?;
+        for issue in issues {
+            writer.write_record(self.columns.iter().map(|column| column.value(issue)))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the issues as a CSV string.
+    pub fn to_string<'a, I>(&self, issues: I) -> Result<String>
+        where
+            I: IntoIterator<Item=&'a model::Issue>,
+    {
+        let mut buf = Vec::new();
+        self.write(issues, &mut buf)?;
+        Ok(String::from_utf8(buf).expect("CSV of strings is not UTF-8"))
+    }
+}
+
+impl Default for CsvWriter {
+    fn default() -> Self {
+        CsvWriter::new()
+    }
+}
+
+#[test]
+fn csv_columns_parse() {
+    assert_eq!(
+        CsvColumn::parse_list("number, Title,labels").unwrap(),
+        vec![CsvColumn::Number, CsvColumn::Title, CsvColumn::Labels],
+    );
+    assert!(CsvColumn::parse_list("number,votes").is_err());
+}
@@ -1,9 +1,33 @@
 
+use std::fmt::{self, Display, Formatter};
+
+use hyper::StatusCode;
+
 error_chain!{
     errors {
-        Request(t: String) {
-            description("invalid request name")
-            display("request failed: '{}'", t)
+        NotFound(e: ApiError) {
+            description("github resource not found")
+            display("not found: {}", e)
+        }
+        Unauthorized(e: ApiError) {
+            description("github authentication failed")
+            display("unauthorized: {}", e)
+        }
+        RateLimited(e: ApiError) {
+            description("github rate limit exceeded")
+            display("rate limited: {}", e)
+        }
+        Forbidden(e: ApiError) {
+            description("github request forbidden")
+            display("forbidden: {}", e)
+        }
+        Unprocessable(e: ApiError) {
+            description("github request invalid")
+            display("unprocessable: {}", e)
+        }
+        Request(e: ApiError) {
+            description("github request failed")
+            display("request failed: {}", e)
         }
     }
     foreign_links {
@@ -14,7 +38,135 @@ error_chain!{
         HbRender(::handlebars::RenderError);
         NativeTls(::native_tls::Error);
         Json(::serde_json::Error);
+        Yaml(::serde_yaml::Error);
+        Toml(::toml::ser::Error);
+        Csv(::csv::Error);
         Utf8(::std::str::Utf8Error);
         EnvVar(::std::env::VarError);
     }
-}
\ No newline at end of file
+}
+
+/// A github API request which responded with a non-success status.
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// URL of the failed request.
+    pub url: String,
+    /// Github's error message, if the body parsed as one.
+    pub message: Option<String>,
+    /// Github's link to documentation on the error, if given.
+    pub documentation_url: Option<String>,
+    /// Raw response body.
+    pub body: String,
+}
+
+/// JSON body of github error responses.
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    message: Option<String>,
+    documentation_url: Option<String>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, url: &str, body: &[u8]) -> Self {
+        let parsed = ::serde_json::from_slice::<ApiErrorBody>(body).ok();
+        ApiError {
+            status,
+            url: url.to_owned(),
+            message: parsed.as_ref().and_then(|b| b.message.clone()),
+            documentation_url: parsed.and_then(|b| b.documentation_url),
+            body: String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+
+    /// Classify into the error kind matching the status.
+    pub fn into_kind(self, rate_limited: bool) -> ErrorKind {
+        match self.status {
+            _ if rate_limited => ErrorKind::RateLimited(self),
+            StatusCode::NotFound => ErrorKind::NotFound(self),
+            StatusCode::Unauthorized => ErrorKind::Unauthorized(self),
+            StatusCode::Forbidden => ErrorKind::Forbidden(self),
+            StatusCode::UnprocessableEntity => ErrorKind::Unprocessable(self),
+            _ => ErrorKind::Request(self),
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} from {}", self.status, self.url)?;
+        match self.message {
+            Some(ref message) => write!(f, ": {}", message)?,
+            None if !self.body.is_empty() => write!(f, ": '{}'", self.body)?,
+            None => (),
+        };
+        if let Some(ref documentation_url) = self.documentation_url {
+            write!(f, " (see {})", documentation_url)?;
+        }
+        Ok(())
+    }
+}
+
+impl ErrorKind {
+    /// The failed github API request, if this is an API error.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match *self {
+            ErrorKind::NotFound(ref e)
+            | ErrorKind::Unauthorized(ref e)
+            | ErrorKind::RateLimited(ref e)
+            | ErrorKind::Forbidden(ref e)
+            | ErrorKind::Unprocessable(ref e)
+            | ErrorKind::Request(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn api_error_kinds() {
+    use hyper::Headers;
+    use crate::rate_limit::is_rate_limited;
+
+    let classify = |status: StatusCode, body: &str| -> ErrorKind {
+        let rate_limited = is_rate_limited(status, &Headers::new(), body.as_bytes());
+        ApiError::new(status, "https://api.github.com/x", body.as_bytes())
+            .into_kind(rate_limited)
+    };
+    let name = |kind: &ErrorKind| match *kind {
+        ErrorKind::NotFound(_) => "not found",
+        ErrorKind::Unauthorized(_) => "unauthorized",
+        ErrorKind::RateLimited(_) => "rate limited",
+        ErrorKind::Forbidden(_) => "forbidden",
+        ErrorKind::Unprocessable(_) => "unprocessable",
+        ErrorKind::Request(_) => "request",
+        _ => "other",
+    };
+
+    let not_found = classify(
+        StatusCode::NotFound,
+        r#"{"message": "Not Found", "documentation_url": "https://docs.github.com/rest"}"#,
+    );
+    assert_eq!(name(&not_found), "not found");
+    let e = not_found.api_error().unwrap();
+    assert_eq!(e.message.as_ref().unwrap(), "Not Found");
+    assert_eq!(e.documentation_url.as_ref().unwrap(), "https://docs.github.com/rest");
+    assert_eq!(e.url, "https://api.github.com/x");
+
+    assert_eq!(name(&classify(StatusCode::Unauthorized, r#"{"message": "Bad credentials"}"#)), "unauthorized");
+    assert_eq!(
+        name(&classify(StatusCode::Forbidden, r#"{"message": "API rate limit exceeded for 1.2.3.4."}"#)),
+        "rate limited",
+    );
+    assert_eq!(
+        name(&classify(StatusCode::Forbidden, r#"{"message": "Resource not accessible by integration"}"#)),
+        "forbidden",
+    );
+    assert_eq!(name(&classify(StatusCode::UnprocessableEntity, r#"{"message": "Validation Failed"}"#)), "unprocessable");
+
+    // bodies which aren't github's JSON errors are kept raw
+    let bad_gateway = classify(StatusCode::BadGateway, "<html>bad gateway</html>");
+    assert_eq!(name(&bad_gateway), "request");
+    let e = bad_gateway.api_error().unwrap();
+    assert!(e.message.is_none());
+    assert_eq!(e.body, "<html>bad gateway</html>");
+}
@@ -0,0 +1,161 @@
+
+use crate::{
+    model,
+    error::*,
+    query::IssueQuery,
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Name of the state file, within the output directory.
+pub const STATE_FILE_NAME: &'static str = ".github-issues-export.json";
+
+/// Seconds by which the time of a listing is moved back, before it's
+/// recorded as the time up to which issues were exported.
+const WATERMARK_MARGIN_SECS: i64 = 60;
+
+/// What a previous export wrote into an output directory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ExportState {
+    /// Time up to which exports listed every issue matching a query's
+    /// filters, by their `IssueQuery::filter_key`.
+    ///
+    /// Issues matching the filters, and updated since, may not have been
+    /// exported yet.
+    #[serde(default)]
+    pub last_updated: BTreeMap<String, DateTime<Utc>>,
+    /// Relative path of the file written for each issue, by number.
+    pub files: BTreeMap<u64, PathBuf>,
+    /// Relative paths of every file which exports wrote, and which are
+    /// still current, including the files of issues and assets.
+    #[serde(default)]
+    pub manifest: BTreeSet<PathBuf>,
+}
+
+impl ExportState {
+    /// Load the state from an output directory, or an empty state if the
+    /// directory has none.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let contents = match fs::read(dir.as_ref().join(STATE_FILE_NAME)) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(ExportState::default());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        ::serde_json::from_slice(&contents)
+            .chain_err(|| "Could not parse export state file")
+    }
+
+    /// Save the state into an output directory.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let mut f = File::create(dir.as_ref().join(STATE_FILE_NAME))?;
+        f.write_all(&::serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Files which previous exports wrote, but which aren't among the
+    /// given current files.
+    ///
+    /// Paths which would lead outside of the output directory are never
+    /// considered stale, in case the state file was tampered with.
+    pub fn stale_files(&self, current: &BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+        self.manifest.iter()
+            .chain(self.files.values())
+            .filter(|path| !current.contains(*path))
+            .filter(|path| path.components().all(|component| match component {
+                Component::Normal(_) => true,
+                _ => false,
+            }))
+            .cloned()
+            .collect()
+    }
+
+    /// Time up to which exports listed every issue matching the filters of
+    /// a query, to fetch only issues updated since.
+    pub fn last_updated(&self, query: &IssueQuery) -> Option<DateTime<Utc>> {
+        self.last_updated.get(&query.filter_key()).cloned()
+    }
+
+    /// Record that every issue matching a query, and updated before a
+    /// listing of them started, was exported.
+    ///
+    /// `listed_at` is the server's time when the listing started, rather
+    /// than the newest `updated_at` listed, since issues may be updated
+    /// while later pages are listed. It's moved back by a margin, to
+    /// allow for github's clocks disagreeing.
+    ///
+    /// Only call this after exporting every issue which the query listed.
+    /// Queries `since` a later time than the query's `last_updated` leave
+    /// it unchanged, since they skipped over issues updated in between.
+    pub fn advance(&mut self, query: &IssueQuery, listed_at: DateTime<Utc>) {
+        let key = query.filter_key();
+        let last = self.last_updated.get(&key).cloned();
+        if let Some(since) = query.since {
+            if last.map(|last| since > last).unwrap_or(true) {
+                return;
+            }
+        }
+        let watermark = listed_at - Duration::seconds(WATERMARK_MARGIN_SECS);
+        if last.map(|last| last < watermark).unwrap_or(true) {
+            self.last_updated.insert(key, watermark);
+        }
+    }
+
+    /// Record that an issue was written to a path.
+    ///
+    /// Returns the path previously written for that issue, if it differs,
+    /// such as when the issue was renamed.
+    pub fn record(&mut self, issue: &model::Issue, path: PathBuf) -> Option<PathBuf> {
+        self.files.insert(issue.number, path.clone())
+            .filter(|old_path| *old_path != path)
+    }
+}
+
+#[test]
+fn stale_files_within_dir() {
+    let mut state = ExportState::default();
+    state.files.insert(1, PathBuf::from("001-old-title.md"));
+    state.manifest.insert(PathBuf::from("index.html"));
+    state.manifest.insert(PathBuf::from("../outside.md"));
+    let current = vec![PathBuf::from("index.html")].into_iter().collect();
+    assert_eq!(
+        state.stale_files(&current),
+        vec![PathBuf::from("001-old-title.md")].into_iter().collect(),
+    );
+}
+
+#[test]
+fn filtered_export_keeps_watermark() {
+    use chrono::TimeZone;
+    use crate::IssueState;
+
+    let date = |y, m, d| Utc.ymd(y, m, d).and_hms(0, 0, 0);
+    let margin = Duration::seconds(WATERMARK_MARGIN_SECS);
+    let all = IssueQuery::new().state(IssueState::All);
+    let bugs = IssueQuery::new().state(IssueState::All).label("bug");
+    let mut state = ExportState::default();
+    state.advance(&all, date(2020, 1, 1));
+    state.advance(&bugs, date(2020, 6, 1));
+    assert_eq!(state.last_updated(&all), Some(date(2020, 1, 1) - margin));
+    assert_eq!(
+        state.last_updated(&all.clone().since(date(2019, 1, 1))),
+        Some(date(2020, 1, 1) - margin),
+    );
+    assert_eq!(state.last_updated(&IssueQuery::new().label("bug")), None);
+
+    // a gap since the last export
+    state.advance(&all.clone().since(date(2021, 1, 1)), date(2021, 6, 1));
+    assert_eq!(state.last_updated(&all), Some(date(2020, 1, 1) - margin));
+
+    // incremental
+    state.advance(&all.clone().since(date(2020, 1, 1) - margin), date(2021, 6, 1));
+    assert_eq!(state.last_updated(&all), Some(date(2021, 6, 1) - margin));
+}
@@ -0,0 +1,113 @@
+
+use crate::{
+    model,
+    error::*,
+};
+
+use chrono::{DateTime, Utc};
+
+/// Formats of front matter block, for static site generators.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Deserialize)]
+pub enum FrontMatter {
+    /// YAML between `---` lines, as used by Jekyll and Hugo.
+    Yaml,
+    /// TOML between `+++` lines, as used by Hugo and Zola.
+    Toml,
+}
+
+/// Issue metadata included in front matter.
+#[derive(Serialize)]
+struct Fields<'a> {
+    title: &'a str,
+    number: u64,
+    state: &'a str,
+    author: &'a str,
+    url: &'a str,
+    labels: Vec<&'a str>,
+    assignees: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    milestone: Option<&'a str>,
+    date: DateTime<Utc>,
+    updated: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    closed: Option<DateTime<Utc>>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(issue: &'a model::Issue) -> Self {
+        Fields {
+            title: &issue.title,
+            number: issue.number,
+            state: &issue.state,
+            author: &issue.user.login,
+            url: &issue.html_url,
+            labels: issue.labels.iter()
+                .map(|label| label.name.as_str())
+                .collect(),
+            assignees: issue.assignees.iter()
+                .map(|user| user.login.as_str())
+                .collect(),
+            milestone: issue.milestone.as_ref()
+                .map(|milestone| milestone.title.as_str()),
+            date: issue.created_at,
+            updated: issue.updated_at,
+            closed: issue.closed_at,
+        }
+    }
+}
+
+impl FrontMatter {
+    /// Render the front matter block for an issue, including delimiters
+    /// and a trailing blank line.
+    pub fn render(&self, issue: &model::Issue) -> Result<String> {
+        let fields = Fields::new(issue);
+        Ok(match *self {
+            FrontMatter::Yaml => {
+                let yaml = ::serde_yaml::to_string(&fields)?;
+                let yaml = yaml.trim_start_matches("---\n").trim_end();
+                format!("---\n{}\n---\n\n", yaml)
+            }
+            FrontMatter::Toml => {
+                let toml = ::toml::to_string(&fields)?;
+                format!("+++\n{}\n+++\n\n", toml.trim_end())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+fn field<'a>(block: &'a str, prefix: &str) -> Option<&'a str> {
+    block.lines()
+        .find(|line| line.starts_with(prefix))
+        .map(|line| line[prefix.len()..].trim_matches('"'))
+}
+
+#[test]
+fn yaml_front_matter() {
+    let issue = model::test_issue(1, "an issue", "");
+    let block = FrontMatter::Yaml.render(&issue).unwrap();
+
+    assert!(block.starts_with("---\n"), "{}", block);
+    assert!(block.ends_with("\n---\n\n"), "{}", block);
+    assert_eq!(field(&block, "number: "), Some("1"));
+    assert_eq!(field(&block, "author: "), Some("octocat"));
+    assert_eq!(field(&block, "date: "), Some("2019-01-01T00:00:00Z"));
+    assert_eq!(field(&block, "assignees: "), Some("[]"));
+    assert_eq!(field(&block, "milestone:"), None);
+    assert_eq!(field(&block, "closed:"), None);
+}
+
+#[test]
+fn toml_front_matter() {
+    let issue = model::test_issue(1, "an issue", "");
+    let block = FrontMatter::Toml.render(&issue).unwrap();
+
+    assert!(block.starts_with("+++\n"), "{}", block);
+    assert!(block.ends_with("\n+++\n\n"), "{}", block);
+    assert_eq!(field(&block, "number = "), Some("1"));
+    assert_eq!(field(&block, "author = "), Some("octocat"));
+    assert_eq!(field(&block, "date = "), Some("2019-01-01T00:00:00Z"));
+    assert_eq!(field(&block, "assignees = "), Some("[]"));
+    assert_eq!(field(&block, "milestone ="), None);
+    assert_eq!(field(&block, "closed ="), None);
+}
@@ -0,0 +1,186 @@
+
+use std::borrow::Cow;
+
+use ammonia;
+use comrak::{
+    self,
+    ComrakOptions,
+    ComrakPlugins,
+    plugins::syntect::SyntectAdapter,
+};
+
+/// Relative path of the stylesheet which standalone pages link to.
+pub const STYLESHEET_PATH: &'static str = "style.css";
+
+/// Stylesheet of standalone pages.
+pub const STYLESHEET: &'static str = include_str!("style.css");
+
+/// Wrap an HTML fragment into a standalone document, linking to the
+/// stylesheet at `stylesheet_href`, relative to the page.
+pub fn page(title: &str, stylesheet_href: &str, body: &str) -> String {
+    let style = format!(
+        "<link rel=\"stylesheet\" href=\"{}\">",
+        escape(stylesheet_href),
+    );
+    document(title, &style, body)
+}
+
+/// Wrap an HTML fragment into a self-contained document, with the
+/// stylesheet inlined.
+pub fn inline_page(title: &str, body: &str) -> String {
+    let style = format!("<style>\n{}</style>", STYLESHEET);
+    document(title, &style, body)
+}
+
+fn document(title: &str, style: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+        <html>\n\
+        <head>\n\
+        <meta charset=\"utf-8\">\n\
+        <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+        <title>{}</title>\n\
+        {}\n\
+        </head>\n\
+        <body>\n\
+        {}\n\
+        </body>\n\
+        </html>\n",
+        escape(title),
+        style,
+        body,
+    )
+}
+
+/// Syntect theme which fenced code blocks are highlighted with.
+const HIGHLIGHT_THEME: &'static str = "InspiredGitHub";
+
+/// CSS properties of the inline styles which syntax highlighting
+/// produces, and which are kept by sanitization.
+const HIGHLIGHT_PROPERTIES: &'static [&'static str] = &[
+    "color",
+    "background-color",
+    "font-weight",
+    "font-style",
+    "text-decoration",
+];
+
+/// GitHub-flavored markdown to HTML conversion, with syntax highlighting
+/// of fenced code blocks.
+///
+/// The HTML is sanitized, since issue bodies and comments are user
+/// provided, and may contain arbitrary inline HTML.
+pub struct Markdown {
+    highlighter: SyntectAdapter<'static>,
+    sanitizer: ammonia::Builder<'static>,
+}
+
+impl Markdown {
+    /// Construct, loading the syntax definitions and highlighting theme.
+    pub fn new() -> Self {
+        let mut sanitizer = ammonia::Builder::default();
+        sanitizer
+            // task list items
+            .add_tags(&["input"])
+            .add_tag_attributes("input", &["type", "checked", "disabled"])
+            // syntax highlighting
+            .add_tag_attributes("pre", &["style"])
+            .add_tag_attributes("span", &["style"])
+            .attribute_filter(filter_attribute);
+
+        Markdown {
+            highlighter: SyntectAdapter::new(HIGHLIGHT_THEME),
+            sanitizer,
+        }
+    }
+
+    /// Convert markdown into a sanitized HTML fragment.
+    ///
+    /// Supports the GFM extensions of tables, task lists, strikethrough
+    /// and autolinks, and renders line breaks as github does in issues.
+    pub fn to_html(&self, md: &str) -> String {
+        let mut options = ComrakOptions::default();
+        options.extension.strikethrough = true;
+        options.extension.table = true;
+        options.extension.autolink = true;
+        options.extension.tasklist = true;
+        options.render.hardbreaks = true;
+        // inline HTML is sanitized afterwards
+        options.render.unsafe_ = true;
+
+        let mut plugins = ComrakPlugins::default();
+        plugins.render.codefence_syntax_highlighter = Some(&self.highlighter);
+
+        let html = comrak::markdown_to_html_with_plugins(md, &options, &plugins);
+        self.sanitizer.clean(&html).to_string()
+    }
+}
+
+/// Restrict the attribute values allowed by the sanitizer to those
+/// which task lists and syntax highlighting produce.
+fn filter_attribute<'u>(
+    element: &str,
+    attribute: &str,
+    value: &'u str,
+) -> Option<Cow<'u, str>> {
+    match (element, attribute) {
+        ("input", "type") if value != "checkbox" => None,
+        (_, "style") => {
+            let style = value.split(';')
+                .map(|declaration| declaration.trim())
+                .filter(|declaration| declaration.split(':').next()
+                    .map(|property| HIGHLIGHT_PROPERTIES.contains(&property.trim()))
+                    .unwrap_or(false))
+                .collect::<Vec<_>>()
+                .join("; ");
+            if style.is_empty() {
+                None
+            } else {
+                Some(style.into())
+            }
+        }
+        _ => Some(value.into()),
+    }
+}
+
+/// Escape text for use within HTML.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[test]
+fn escape_html() {
+    assert_eq!(
+        escape(r#"<a href="x">Tom & Jerry's</a>"#),
+        "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;",
+    );
+}
+
+#[test]
+fn gfm_sanitized() {
+    let md = Markdown::new();
+    let html = md.to_html(
+        "~~old~~ https://example.com\n\n\
+        - [x] done\n\n\
+        | a |\n|---|\n| b |\n\n\
+        <span style=\"position: fixed; color: red\">x</span>\
+        <script>alert(1)</script>",
+    );
+    assert!(html.contains("<del>old</del>"));
+    assert!(html.contains("<a href=\"https://example.com\""));
+    assert!(html.contains("type=\"checkbox\""));
+    assert!(html.contains("<table>"));
+    assert!(html.contains("<span style=\"color: red\">x</span>"));
+    assert!(!html.contains("script"));
+}
@@ -0,0 +1,50 @@
+
+use crate::{
+    model,
+    error::*,
+};
+
+use std::io::Write;
+
+/// Default name of the JSON file which issues are exported into.
+pub const JSON_FILE_NAME: &'static str = "issues.json";
+
+/// Serialize issues into a pretty printed JSON array.
+///
+/// Each element is an `IssueWithComments`, an object of the `issue`,
+/// its `comments`, and its timeline `events`, which is empty unless they
+/// were fetched. The issue and comment objects have the fields of the
+/// github REST API which the `model` module declares, with absent
+/// optional fields as `null`. Events are objects tagged by their
+/// kebab-case `event` field. New fields may be added, but existing ones
+/// won't be renamed or removed.
+pub fn to_json(issues: &[model::IssueWithComments]) -> Result<String> {
+    Ok(::serde_json::to_string_pretty(issues)?)
+}
+
+/// Serialize issues as JSON Lines, one compact JSON object per line, in
+/// the schema of `to_json`.
+///
+/// The writer is flushed after each line, so that consumers can process
+/// issues as they are written.
+pub fn write_json_lines<W: Write>(
+    issues: &[model::IssueWithComments],
+    mut w: W,
+) -> Result<()> {
+    for issue in issues {
+        write_json_line(issue, &mut w)?;
+    }
+    Ok(())
+}
+
+/// Serialize an issue as a line of JSON Lines, in the schema of
+/// `to_json`, and flush the writer.
+pub fn write_json_line<W: Write>(
+    issue: &model::IssueWithComments,
+    mut w: W,
+) -> Result<()> {
+    ::serde_json::to_writer(&mut w, issue)?;
+    w.write_all(b"\n")?;
+    w.flush()?;
+    Ok(())
+}
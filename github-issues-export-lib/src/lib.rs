@@ -13,36 +13,55 @@ extern crate serde_derive;
 extern crate serde_json;
 extern crate slug;
 extern crate docopt;
-extern crate markdown;
+extern crate comrak;
+extern crate ammonia;
+extern crate csv;
+extern crate serde_yaml;
+extern crate toml;
+extern crate once_cell;
 
 pub extern crate tokio_core;
+pub extern crate chrono;
+extern crate chrono_tz;
 
 use crate::{
     error::*,
     auth::GithubAuth,
+    builder::GithubBuilder,
+    rate_limit::{RateLimit, RetryPolicy},
+    cache::{CacheEntry, HttpCache},
+    query::{IssueQuery, IssueRange},
 };
 
 use std::{
+    collections::BTreeMap,
     env,
     fmt::{self, Display, Formatter},
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::SystemTime,
 };
 use futures::{
     {Future, Stream},
-    future,
+    future::{self, Either, Loop},
+    stream,
 };
 use hyper::{
-    {Client, Method, Request, Uri},
+    {Chunk, Client, Headers, Method, Request, StatusCode, Uri},
     header::{
+        self,
         Authorization,
         ContentLength,
         ContentType,
+        Link,
+        RelationType,
         UserAgent,
     },
 };
+use chrono::{DateTime, Utc};
 
 pub use tokio_core::reactor::Core as TokioCore;
+use tokio_core::reactor::{Handle, Timeout};
 
 /// Github resource data model.
 pub mod model;
@@ -56,12 +75,56 @@ pub mod auth;
 /// Rendering issues to markdown.
 pub mod render;
 
+/// Configuring and constructing the github access service.
+pub mod builder;
+
+/// Github rate limit tracking and backoff.
+pub mod rate_limit;
+
+/// On-disk caching of github responses.
+pub mod cache;
+
+/// Filtering and ordering issue listings.
+pub mod query;
+
+/// Tracking what was exported into an output directory, as a manifest.
+pub mod export_state;
+
+/// Front matter blocks for static site generators.
+pub mod front_matter;
+
+/// Downloading images and attachments which issues link to.
+pub mod assets;
+
+/// Linking references between issues.
+pub mod references;
+
+/// JSON and JSON Lines serialization of exported issues.
+pub mod json;
+
+/// CSV export of issue metadata.
+pub mod csv_export;
+
+/// Markdown to HTML conversion, and standalone HTML page structure.
+pub mod html;
+
+/// Naming the files which issues are saved at.
+pub mod path_template;
+
 pub mod prelude {
     pub use crate::{
         Github,
+        CommentsFailure,
         IssueState,
+        builder::GithubBuilder,
+        rate_limit::{RateLimit, RetryPolicy},
+        cache::HttpCache,
+        query::{IssueQuery, IssueRange, IssueSort, Query, SortDirection},
         RepoLocation,
         render::IssueRenderer,
+        path_template::PathTemplate,
+        front_matter::FrontMatter,
+        csv_export::{CsvColumn, CsvWriter},
         auth::GithubAuth,
         model as gh_model,
         error as gh_error,
@@ -73,27 +136,46 @@ pub mod prelude {
 #[derive(Clone)]
 pub struct Github {
     client: Arc<Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>>,
+    handle: Handle,
+    base_url: Arc<String>,
     user_agent: UserAgent,
-    token: Authorization<String>,
+    token: Option<Authorization<String>>,
+    per_page: u32,
+    concurrency: usize,
+    retry_policy: RetryPolicy,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+    cache: Option<HttpCache>,
 }
 
-const GITHUB_API_ENDPOINT: &'static str = "https://api.github.com";
+/// Default number of elements to request per page of a paginated resource.
+///
+/// This is the maximum which github allows.
+pub const DEFAULT_PER_PAGE: u32 = 100;
+
+/// Default maximum number of requests in flight when fanning out over
+/// many issues.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Maximum number of redirects followed when downloading a file.
+const MAX_REDIRECTS: u32 = 5;
 
 impl Github {
-    /// Low level constructor. Consider using `from_auth`.
+    /// Low level constructor. Consider using `from_auth` or `builder`.
     pub fn new(
         handle: &tokio_core::reactor::Handle,
         agent: UserAgent,
         token: &str,
     ) -> Result<Self> {
-        let client = hyper::Client::configure()
-            .connector(hyper_tls::HttpsConnector::new(4, handle)?)
-            .build(handle);
-        Ok(Self {
-            client: Arc::new(client),
-            user_agent: agent,
-            token: Authorization(format!("token {}", token)),
-        })
+        GithubBuilder::new()
+            .user_agent(agent)
+            .auth(token.to_owned())
+            .build(handle)
+    }
+
+    /// Builder for configuring the service, such as to target a github
+    /// enterprise server.
+    pub fn builder() -> GithubBuilder {
+        GithubBuilder::new()
     }
 
     /// High level constructor.
@@ -103,69 +185,308 @@ impl Github {
         where
             A: Into<GithubAuth>
     {
-        // resolve user agent at compile time
-        let user_agent = UserAgent::new(concat!(
-        env!("CARGO_PKG_NAME"),
-        "/",
-        env!("CARGO_PKG_VERSION"),
-        ));
+        GithubBuilder::new()
+            .auth(auth)
+            .build_with_core()
+    }
 
-        // create the tokio reactor core
-        let tokio_core = TokioCore::new()?;
+    /// Base URL of the API this service targets.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
 
-        // delegate to low-level constructor
-        Github::new(
-            &tokio_core.handle(),
-            user_agent,
-            &auth.into().token,
-        ).map(move |github|
-            (github, tokio_core)
-        )
+    /// Rate limit budget, as reported by the most recent response.
+    ///
+    /// Shared between clones of this service.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
     }
 
-    /// GET request, retrieve and parse.
+    /// Absolute URL of an API path, relative to the base URL.
+    pub fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// Send a GET request, and retrieve the raw response.
     ///
-    /// Other methods exist as typed helpers.
-    pub fn get<T>(&self, endpoint: &str) -> impl Future<Item=T, Error=Error>
-        where
-            T: serde::de::DeserializeOwned,
-    {
-        let url = Uri::from_str(endpoint).expect("Could not parse uri");
+    /// Rate limited requests are waited on and retried according to the
+    /// retry policy. If a cache is configured, cached responses are
+    /// revalidated, and replayed if not modified.
+    fn send_get(
+        &self,
+        endpoint: &str,
+    ) -> impl Future<Item=(StatusCode, Headers, Chunk), Error=Error> {
+        let url = match Uri::from_str(endpoint) {
+            Ok(url) => url,
+            Err(e) => return Either::A(future::err(
+                format!("invalid URL {}: {}", endpoint, e).into()
+            )),
+        };
+        let cache = self.cache.clone();
+        let cached = cache.as_ref().and_then(|cache| cache.load(endpoint));
+        let validators = cached.clone();
+        let endpoint = endpoint.to_owned();
+        let github = self.clone();
+        let resp = future::loop_fn(0, move |attempt| {
+            let github = github.clone();
+            github.send_get_once(url.clone(), validators.as_ref())
+                .and_then(move |(status_code, headers, chunk)| {
+                    let delay =
+                        if rate_limit::is_rate_limited(status_code, &headers, &chunk) {
+                            github.retry_policy.retry_delay(attempt, &headers)
+                        } else {
+                            None
+                        };
+                    match delay {
+                        Some(delay) => Either::A(
+                            future::result(Timeout::new(delay, &github.handle))
+                                .flatten()
+                                .from_err::<Error>()
+                                .map(move |()| Loop::Continue(attempt + 1))
+                        ),
+                        None => Either::B(future::ok(
+                            Loop::Break((status_code, headers, chunk))
+                        )),
+                    }
+                })
+        });
+        Either::B(resp.and_then(move |(status_code, headers, chunk)| {
+            match (cache, cached) {
+                (Some(_), Some(entry)) if status_code == StatusCode::NotModified =>
+                    Ok(entry.replay(headers)),
+                (Some(cache), _) if status_code.is_success() => {
+                    cache.store(&endpoint, &headers, &chunk)?;
+                    Ok((status_code, headers, chunk))
+                },
+                _ => Ok((status_code, headers, chunk)),
+            }
+        }))
+    }
+
+    /// Send a single GET request, and retrieve the raw response.
+    ///
+    /// Also records the rate limit budget reported by the response.
+    fn send_get_once(
+        &self,
+        url: Uri,
+        validators: Option<&CacheEntry>,
+    ) -> impl Future<Item=(StatusCode, Headers, Chunk), Error=Error> {
         let mut req = Request::new(Method::Get, url);
         req.headers_mut().set(self.user_agent.clone());
-        req.headers_mut().set(self.token.clone());
+        if let Some(ref token) = self.token {
+            req.headers_mut().set(token.clone());
+        }
+        if let Some(entry) = validators {
+            entry.set_validators(req.headers_mut());
+        }
         req.headers_mut().set(ContentType::json());
         req.headers_mut().set(ContentLength(0));
         let resp = self.client.request(req);
-        resp.map_err(Error::from).and_then(|resp| {
+        let rate_limit = self.rate_limit.clone();
+        resp.map_err(Error::from).and_then(move |resp| {
             let status_code = resp.status();
-            let body = resp.body().concat2().from_err();
-            body.and_then(move |chunk| {
-                if !status_code.is_success() {
-                    let resp = String::from(::std::str::from_utf8(&chunk)?);
-                    Err(ErrorKind::Request(resp).into())
-                } else {
-                    let value: T = ::serde_json::from_slice(&chunk)
-                        .chain_err(|| "Could not parse response from server")?;
-                    Ok(value)
-                }
-            })
+            let headers = resp.headers().clone();
+            if let Some(budget) = RateLimit::from_headers(&headers) {
+                *rate_limit.lock().unwrap() = Some(budget);
+            }
+            resp.body().concat2().from_err()
+                .map(move |chunk| (status_code, headers, chunk))
         })
     }
 
+    /// Download a file which an issue links to, such as an attached image,
+    /// following redirects.
+    ///
+    /// The auth token is not sent, since such files are hosted outside of
+    /// the API.
+    pub fn download(&self, url: &str) -> impl Future<Item=Vec<u8>, Error=Error> {
+        let github = self.clone();
+        future::loop_fn((url.to_owned(), 0), move |(url, redirects)| {
+            let client = github.client.clone();
+            let user_agent = github.user_agent.clone();
+            future::result(Uri::from_str(&url)
+                .map_err(|e| Error::from(format!("invalid URL {}: {}", url, e))))
+                .and_then(move |uri| {
+                    let mut req = Request::new(Method::Get, uri);
+                    req.headers_mut().set(user_agent);
+                    client.request(req).from_err()
+                })
+                .and_then(move |resp| {
+                    let status_code = resp.status();
+                    let location = resp.headers().get_raw("Location")
+                        .and_then(|raw| raw.one())
+                        .and_then(|location| std::str::from_utf8(location).ok())
+                        .map(|location| resolve_location(&url, location));
+                    resp.body().concat2().from_err().and_then(move |chunk| {
+                        match location {
+                            Some(location) if status_code.is_redirection() => {
+                                if redirects >= MAX_REDIRECTS {
+                                    bail!("too many redirects from {}", url);
+                                }
+                                Ok(Loop::Continue((location, redirects + 1)))
+                            }
+                            _ if status_code.is_success() => Ok(Loop::Break(chunk.to_vec())),
+                            _ => Err(ApiError::new(status_code, &url, &chunk)
+                                .into_kind(false)
+                                .into()),
+                        }
+                    })
+                })
+        })
+    }
+
+    /// Download files which issues link to, fetching concurrently.
+    ///
+    /// Rather than failing as a whole, produces the result of each
+    /// download alongside its URL, in order.
+    pub fn download_all(
+        &self,
+        urls: Vec<String>,
+    ) -> impl Future<Item=Vec<(String, Result<Vec<u8>>)>, Error=Error> {
+        let github = self.clone();
+        stream::iter_ok::<_, Error>(urls)
+            .map(move |url| github.download(&url)
+                .then(move |result| Ok::<_, Error>((url, result))))
+            .buffered(self.concurrency)
+            .collect()
+    }
+
+    /// GET request, retrieve and parse.
+    ///
+    /// Other methods exist as typed helpers.
+    pub fn get<T>(&self, endpoint: &str) -> impl Future<Item=T, Error=Error>
+        where
+            T: serde::de::DeserializeOwned,
+    {
+        self.get_page(endpoint).map(|(value, _)| value)
+    }
+
+    /// GET request, retrieve and parse, along with the URL of the next
+    /// page, if the response links to one.
+    pub fn get_page<T>(
+        &self,
+        endpoint: &str,
+    ) -> impl Future<Item=(T, Option<String>), Error=Error>
+        where
+            T: serde::de::DeserializeOwned,
+    {
+        self.get_dated_page(endpoint).map(|(value, next, _)| (value, next))
+    }
+
+    /// GET request, retrieve and parse, along with the URL of the next
+    /// page, and the server's `Date` of the response, if given.
+    pub fn get_dated_page<T>(
+        &self,
+        endpoint: &str,
+    ) -> impl Future<Item=(T, Option<String>, Option<DateTime<Utc>>), Error=Error>
+        where
+            T: serde::de::DeserializeOwned,
+    {
+        let endpoint = endpoint.to_owned();
+        self.send_get(&endpoint).and_then(move |(status_code, headers, chunk)| {
+            if !status_code.is_success() {
+                let rate_limited =
+                    rate_limit::is_rate_limited(status_code, &headers, &chunk);
+                let error = ApiError::new(status_code, &endpoint, &chunk);
+                Err(error.into_kind(rate_limited).into())
+            } else {
+                let value: T = ::serde_json::from_slice(&chunk)
+                    .chain_err(|| "Could not parse response from server")?;
+                let date = headers.get::<header::Date>()
+                    .map(|date| DateTime::<Utc>::from(SystemTime::from(date.0)));
+                Ok((value, next_page_link(&headers), date))
+            }
+        })
+    }
+
+    /// GET a paginated list resource, following `Link: rel="next"`
+    /// headers until the last page.
+    ///
+    /// Yields the elements of every page as a single stream.
+    pub fn get_paginated<T>(
+        &self,
+        endpoint: &str,
+    ) -> impl Stream<Item=T, Error=Error>
+        where
+            T: serde::de::DeserializeOwned,
+    {
+        self.pages_from(Some(with_query(endpoint, "per_page", self.per_page)))
+    }
+
+    /// Stream the elements of a paginated list resource, starting at the
+    /// given page, if any.
+    fn pages_from<T>(&self, first: Option<String>) -> impl Stream<Item=T, Error=Error>
+        where
+            T: serde::de::DeserializeOwned,
+    {
+        let github = self.clone();
+        stream::unfold(first, move |next| next
+            .map(|url| github.get_page::<Vec<T>>(&url))
+        )
+            .map(|page| stream::iter_ok::<_, Error>(page))
+            .flatten()
+    }
+
+    /// GET a paginated list resource, and collect every page.
+    pub fn get_all<T>(
+        &self,
+        endpoint: &str,
+    ) -> impl Future<Item=Vec<T>, Error=Error>
+        where
+            T: serde::de::DeserializeOwned,
+    {
+        self.get_paginated(endpoint).collect()
+    }
+
+    /// GET all repositories of a user or organization.
+    ///
+    /// For organizations, this includes the private repositories which
+    /// the auth token has access to. For the user of the auth token, this
+    /// includes their private repositories, but for other users, only
+    /// their public repositories.
+    pub fn owner_repos(
+        &self,
+        owner: &str,
+    ) -> impl Future<Item=Vec<model::Repository>, Error=Error> {
+        let github = self.clone();
+        let owner = owner.to_owned();
+        self.get_all(&self.endpoint(&format!("orgs/{}/repos", owner)))
+            .or_else(move |e| match *e.kind() {
+                // not an organization
+                ErrorKind::NotFound(_) => Either::A(github.user_repos(owner)),
+                _ => Either::B(future::err(e)),
+            })
+    }
+
+    /// GET all repositories of a user, including private ones if it's the
+    /// user of the auth token.
+    fn user_repos(
+        &self,
+        owner: String,
+    ) -> impl Future<Item=Vec<model::Repository>, Error=Error> {
+        let github = self.clone();
+        // fails without an auth token
+        self.get::<model::User>(&self.endpoint("user"))
+            .then(move |user| match user {
+                Ok(ref user) if user.login.eq_ignore_ascii_case(&owner) => github.get_all(
+                    &github.endpoint("user/repos?affiliation=owner"),
+                ),
+                _ => github.get_all(&github.endpoint(&format!("users/{}/repos", owner))),
+            })
+    }
+
     /// GET a github issue.
     pub fn issue(
         &self,
         repo: &RepoLocation,
         number: usize,
     ) -> impl Future<Item=model::Issue, Error=Error> {
-        self.get(&format!(
-            "{}/repos/{owner}/{repo}/issues/{number}",
-            GITHUB_API_ENDPOINT,
+        self.get(&self.endpoint(&format!(
+            "repos/{owner}/{repo}/issues/{number}",
             owner = repo.user,
             repo = repo.repo,
             number = number
-        ))
+        )))
     }
 
     /// GET all github issues in a repo.
@@ -174,43 +495,329 @@ impl Github {
         repo: &RepoLocation,
         issue_state: IssueState,
     ) -> impl Future<Item=Vec<model::Issue>, Error=Error> {
-        self.get(&format!(
-            "{}/repos/{}/{}/issues?state={}",
-            GITHUB_API_ENDPOINT,
+        self.issues_stream(repo, issue_state).collect()
+    }
+
+    /// Stream all github issues in a repo, fetching page by page.
+    pub fn issues_stream(
+        &self,
+        repo: &RepoLocation,
+        issue_state: IssueState,
+    ) -> impl Stream<Item=model::Issue, Error=Error> {
+        self.query_issues_stream(repo, &IssueQuery::from(issue_state))
+    }
+
+    /// GET all github issues in a repo which match a query.
+    pub fn query_issues(
+        &self,
+        repo: &RepoLocation,
+        query: &IssueQuery,
+    ) -> impl Future<Item=Vec<model::Issue>, Error=Error> {
+        self.query_issues_stream(repo, query).collect()
+    }
+
+    /// GET all github issues in a repo which match a query, along with
+    /// the server's time when the listing started, if given.
+    ///
+    /// Issues updated before that time are sure to be listed as of their
+    /// latest update, even if updated while later pages were listed.
+    pub fn query_issues_dated(
+        &self,
+        repo: &RepoLocation,
+        query: &IssueQuery,
+    ) -> impl Future<Item=(Vec<model::Issue>, Option<DateTime<Utc>>), Error=Error> {
+        let github = self.clone();
+        let first = with_query(&self.issues_url(repo, query), "per_page", self.per_page);
+        self.get_dated_page::<Vec<model::Issue>>(&first)
+            .and_then(move |(mut issues, next, listed_at)| github.pages_from(next)
+                .collect()
+                .map(move |rest| {
+                    issues.extend(rest);
+                    (issues, listed_at)
+                }))
+    }
+
+    /// GET the github issues in a repo with the given numbers, or within
+    /// the given ranges.
+    ///
+    /// Single issue numbers and bounded ranges are fetched number by
+    /// number, regardless of the query, skipping numbers of ranges which
+    /// aren't issues. Ranges without an end are selected from every issue
+    /// which matches the query, since transferred issues break the order
+    /// of numbers by creation time.
+    pub fn select_issues(
+        &self,
+        repo: &RepoLocation,
+        ranges: &[IssueRange],
+        query: &IssueQuery,
+    ) -> impl Future<Item=Vec<model::Issue>, Error=Error> {
+        // numbers to fetch, and whether they were selected as a range
+        let mut numbers: BTreeMap<u64, bool> = BTreeMap::new();
+        let mut open_ended: Vec<IssueRange> = Vec::new();
+        for range in ranges {
+            match range.end {
+                _ if range.is_single() => {
+                    numbers.insert(range.start, false);
+                }
+                Some(end) => for number in range.start..=end {
+                    numbers.entry(number).or_insert(true);
+                },
+                None => open_ended.push(range.clone()),
+            }
+        }
+
+        let github = self.clone();
+        let fetch_repo = repo.clone();
+        let fetched = stream::iter_ok::<_, Error>(numbers)
+            .map(move |(number, in_range)| github.issue(&fetch_repo, number as usize)
+                .then(move |result| match result {
+                    // transferred issues redirect to their new repo
+                    Ok(ref issue) if in_range && issue.number != number => Ok(None),
+                    Ok(issue) => Ok(Some(issue)),
+                    Err(ref e) if in_range && is_missing(e) => Ok(None),
+                    Err(e) => Err(e),
+                }))
+            .buffered(self.concurrency)
+            .filter_map(|issue| issue)
+            .collect();
+
+        let listed = if open_ended.is_empty() {
+            Either::A(future::ok(Vec::new()))
+        } else {
+            Either::B(self.query_issues_stream(repo, query)
+                .filter(move |issue| open_ended.iter().any(|range| range.contains(issue.number)))
+                .collect())
+        };
+
+        listed.join(fetched).map(|(mut issues, fetched)| {
+            for issue in fetched {
+                if !issues.iter().any(|listed| listed.number == issue.number) {
+                    issues.push(issue);
+                }
+            }
+            issues
+        })
+    }
+
+    /// Stream all github issues in a repo which match a query, fetching
+    /// page by page.
+    pub fn query_issues_stream(
+        &self,
+        repo: &RepoLocation,
+        query: &IssueQuery,
+    ) -> impl Stream<Item=model::Issue, Error=Error> {
+        self.get_paginated(&self.issues_url(repo, query))
+    }
+
+    /// URL of the issues in a repo which match a query.
+    fn issues_url(&self, repo: &RepoLocation, query: &IssueQuery) -> String {
+        let mut url = self.endpoint(&format!(
+            "repos/{}/{}/issues",
             &repo.user,
             &repo.repo,
-            issue_state
-        ))
+        ));
+        let query = query.to_query_string();
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query);
+        }
+        url
+    }
+
+    /// Stream all comments on an issue, fetching page by page.
+    pub fn comments_stream(
+        &self,
+        issue: &model::Issue,
+    ) -> impl Stream<Item=model::Comment, Error=Error> {
+        self.get_paginated(&issue.comments_url)
+    }
+
+    /// Stream the timeline events of an issue, fetching page by page.
+    ///
+    /// Comments and unrecognized events are skipped.
+    pub fn events_stream(
+        &self,
+        issue: &model::Issue,
+    ) -> impl Stream<Item=model::IssueEvent, Error=Error> {
+        self.get_paginated(&format!("{}/timeline", issue.url))
+            .filter(|event| match *event {
+                model::IssueEvent::Other => false,
+                _ => true,
+            })
     }
 
     /// Given a vector of issues already fetched from a repository,
     /// fetch their comments.
+    ///
+    /// Fails as a whole if fetching any issue's comments fails.
     pub fn issue_comments(
         &self,
         issues: Vec<model::Issue>,
     ) -> impl Future<Item=Vec<model::IssueWithComments>, Error=Error> {
+        collect_all(self.issue_details_stream(stream::iter_ok(issues), false))
+    }
+
+    /// Given a vector of issues already fetched from a repository,
+    /// fetch their comments.
+    ///
+    /// Rather than failing as a whole, collects the issues whose comments
+    /// could not be fetched alongside the issues which succeeded.
+    pub fn issue_comments_partial(
+        &self,
+        issues: Vec<model::Issue>,
+    ) -> impl Future<
+        Item=(Vec<model::IssueWithComments>, Vec<CommentsFailure>),
+        Error=Error,
+    > {
+        collect_partial(self.issue_details_stream(stream::iter_ok(issues), false))
+    }
+
+    /// Given a vector of issues already fetched from a repository,
+    /// fetch their comments and timeline events.
+    ///
+    /// Fails as a whole if fetching any issue's history fails.
+    pub fn issue_history(
+        &self,
+        issues: Vec<model::Issue>,
+    ) -> impl Future<Item=Vec<model::IssueWithComments>, Error=Error> {
+        collect_all(self.issue_details_stream(stream::iter_ok(issues), true))
+    }
+
+    /// Given a vector of issues already fetched from a repository,
+    /// fetch their comments and timeline events.
+    ///
+    /// Rather than failing as a whole, collects the issues whose history
+    /// could not be fetched alongside the issues which succeeded.
+    pub fn issue_history_partial(
+        &self,
+        issues: Vec<model::Issue>,
+    ) -> impl Future<
+        Item=(Vec<model::IssueWithComments>, Vec<CommentsFailure>),
+        Error=Error,
+    > {
+        collect_partial(self.issue_details_stream(stream::iter_ok(issues), true))
+    }
+
+    /// Fetch the comments, and optionally timeline events, of each issue
+    /// of a stream, with at most `concurrency` issues in flight, yielding
+    /// in the original order.
+    ///
+    /// Issues are yielded as soon as their details are fetched, so that
+    /// they can be processed while later ones are still being listed.
+    /// Issues whose details could not be fetched are yielded as failures.
+    pub fn issue_details_stream<S>(
+        &self,
+        issues: S,
+        with_events: bool,
+    ) -> impl Stream<Item=DetailsResult, Error=Error>
+        where
+            S: Stream<Item=model::Issue, Error=Error>,
+    {
         let github = self.clone();
 
-        future::join_all({
-            issues.into_iter()
-                .map(move |issue| {
-                    let get_comment = github
-                        .get::<Vec<model::Comment>>(&issue.comments_url);
-                    Future::join(
-                        future::ok(issue),
-                        get_comment,
-                    )
-                })
+        issues
+            .map(move |issue| {
+                let comments = github
+                    .comments_stream(&issue)
+                    .collect();
+                let events = if with_events {
+                    Either::A(github.events_stream(&issue).collect())
+                } else {
+                    Either::B(future::ok(Vec::new()))
+                };
+                comments.join(events)
+                    .then(move |result| Ok::<_, Error>(match result {
+                        Ok((comments, events)) => Ok(model::IssueWithComments {
+                            issue,
+                            comments,
+                            events,
+                        }),
+                        Err(error) => Err(CommentsFailure {
+                            issue,
+                            error,
+                        }),
+                    }))
+            })
+            .buffered(self.concurrency)
+    }
+}
+
+/// An issue whose comments, or timeline events, could not be fetched.
+#[derive(Debug)]
+pub struct CommentsFailure {
+    pub issue: model::Issue,
+    pub error: Error,
+}
+
+/// Result of fetching the details of a single issue.
+pub type DetailsResult = ::std::result::Result<model::IssueWithComments, CommentsFailure>;
+
+/// Collect fetched issue details, failing as a whole on any failure.
+fn collect_all<S>(results: S) -> impl Future<Item=Vec<model::IssueWithComments>, Error=Error>
+    where
+        S: Stream<Item=DetailsResult, Error=Error>,
+{
+    results
+        .and_then(|result| result.map_err(|failure| failure.error))
+        .collect()
+}
+
+/// Collect fetched issue details, separating out the failures.
+fn collect_partial<S>(results: S) -> impl Future<
+    Item=(Vec<model::IssueWithComments>, Vec<CommentsFailure>),
+    Error=Error,
+>
+    where
+        S: Stream<Item=DetailsResult, Error=Error>,
+{
+    results
+        .fold((Vec::new(), Vec::new()), |(mut fetched, mut failed), result| {
+            match result {
+                Ok(issue) => fetched.push(issue),
+                Err(failure) => failed.push(failure),
+            };
+            Ok::<_, Error>((fetched, failed))
         })
-            .map(|issues| issues
-                .into_iter()
-                .map(|(issue, comments)| model::IssueWithComments {
-                    issue,
-                    comments,
-                })
-                .collect::<Vec<model::IssueWithComments>>()
-            )
+}
+
+/// Whether a request failed because the resource doesn't exist, or has
+/// been deleted.
+fn is_missing(e: &Error) -> bool {
+    match *e.kind() {
+        ErrorKind::NotFound(_) => true,
+        ErrorKind::Request(ref e) => e.status == StatusCode::Gone,
+        _ => false,
+    }
+}
+
+/// Find the URL of the next page from a response's `Link` header.
+fn next_page_link(headers: &Headers) -> Option<String> {
+    headers.get::<Link>()?
+        .values()
+        .iter()
+        .find(|value| value.rel()
+            .map(|rels| rels.contains(&RelationType::Next))
+            .unwrap_or(false))
+        .map(|value| value.link().to_owned())
+}
+
+/// Resolve the `Location` of a redirect, which may be relative to the
+/// requested URL's host.
+fn resolve_location(url: &str, location: &str) -> String {
+    if !location.starts_with('/') || location.starts_with("//") {
+        return location.to_owned();
     }
+    let host_start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    let host_end = url[host_start..].find('/')
+        .map(|i| host_start + i)
+        .unwrap_or(url.len());
+    format!("{}{}", &url[..host_end], location)
+}
+
+/// Append a query parameter to a URL, which may already have a query.
+fn with_query<V: Display>(url: &str, key: &str, value: V) -> String {
+    let sep = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}{}={}", url, sep, key, value)
 }
 
 /// Possible states to fetch issues by.
@@ -241,6 +848,18 @@ impl RepoLocation {
     }
 }
 
+impl<'a> From<&'a model::Repository> for RepoLocation {
+    fn from(repo: &'a model::Repository) -> Self {
+        RepoLocation::new(&repo.owner.login, &repo.name)
+    }
+}
+
+impl Display for RepoLocation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.user, self.repo)
+    }
+}
+
 impl IssueState {
     fn to_str(&self) -> &'static str {
         match *self {
@@ -256,3 +875,17 @@ impl Display for IssueState {
         f.write_str(self.to_str())
     }
 }
+
+#[test]
+fn next_page_link_parse() {
+    let mut headers = Headers::new();
+    headers.set_raw("Link", concat!(
+        r#"<https://api.github.com/repositories/1/issues?page=2>; rel="next", "#,
+        r#"<https://api.github.com/repositories/1/issues?page=5>; rel="last""#,
+    ));
+    assert_eq!(
+        next_page_link(&headers).as_ref().map(String::as_str),
+        Some("https://api.github.com/repositories/1/issues?page=2"),
+    );
+    assert_eq!(next_page_link(&Headers::new()), None);
+}
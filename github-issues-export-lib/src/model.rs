@@ -1,11 +1,14 @@
 
+use chrono::{DateTime, Utc};
+
 /// HTTP resource model for github user.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
     pub login: String,
     pub id: u64,
     pub avatar_url: String,
-    pub gravatar_id: String,
+    #[serde(default)]
+    pub gravatar_id: Option<String>,
     pub url: String,
     pub html_url: String,
     pub followers_url: String,
@@ -17,10 +20,14 @@ pub struct User {
     pub repos_url: String,
     pub events_url: String,
     pub received_events_url: String,
+    #[serde(default)]
     pub site_admin: bool,
 }
 
 /// HTTP resource model for github issue.
+///
+/// Github also lists pull requests as issues, which can be told apart by
+/// `pull_request` being present.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Issue {
     pub id: u64,
@@ -31,16 +38,39 @@ pub struct Issue {
     pub html_url: String,
     pub number: u64,
     pub state: String,
+    #[serde(default)]
+    pub state_reason: Option<String>,
     pub title: String,
-    pub body: String,
+    /// Null for issues created without a description.
+    #[serde(default)]
+    pub body: Option<String>,
     pub user: User,
+    #[serde(default)]
     pub labels: Vec<Label>,
     pub assignee: Option<User>,
+    #[serde(default)]
+    pub assignees: Vec<User>,
+    #[serde(default)]
+    pub milestone: Option<Milestone>,
+    #[serde(default)]
     pub locked: bool,
     pub comments: u64,
-    pub closed_at: Option<String>,
-    pub created_at: String,
-    pub updated_at: String,
+    #[serde(default)]
+    pub pull_request: Option<PullRequestRef>,
+    #[serde(default)]
+    pub reactions: Option<Reactions>,
+    pub closed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub closed_by: Option<User>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Issue {
+    /// Whether this is actually a pull request.
+    pub fn is_pull_request(&self) -> bool {
+        self.pull_request.is_some()
+    }
 }
 
 /// HTTP resource model for github issue label.
@@ -49,6 +79,64 @@ pub struct Label {
     pub url: String,
     pub name: String,
     pub color: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// HTTP resource model for github milestone.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Milestone {
+    pub id: u64,
+    pub url: String,
+    pub html_url: String,
+    pub number: u64,
+    pub state: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub creator: Option<User>,
+    #[serde(default)]
+    pub open_issues: u64,
+    #[serde(default)]
+    pub closed_issues: u64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub due_on: Option<DateTime<Utc>>,
+}
+
+/// HTTP resource model for the pull request links of an issue which is
+/// a pull request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PullRequestRef {
+    pub url: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub diff_url: Option<String>,
+    #[serde(default)]
+    pub patch_url: Option<String>,
+    #[serde(default)]
+    pub merged_at: Option<DateTime<Utc>>,
+}
+
+/// HTTP resource model for reaction counts on an issue or comment.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Reactions {
+    pub total_count: u64,
+    #[serde(rename = "+1")]
+    pub plus_one: u64,
+    #[serde(rename = "-1")]
+    pub minus_one: u64,
+    pub laugh: u64,
+    pub hooray: u64,
+    pub confused: u64,
+    pub heart: u64,
+    pub rocket: u64,
+    pub eyes: u64,
 }
 
 /// HTTP resource model for github issue comment.
@@ -57,15 +145,335 @@ pub struct Comment {
     pub id: u64,
     pub url: String,
     pub html_url: String,
-    pub body: String,
+    #[serde(default)]
+    pub body: Option<String>,
     pub user: User,
-    pub created_at: String,
-    pub updated_at: String,
+    #[serde(default)]
+    pub reactions: Option<Reactions>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// HTTP resource model for an event in a github issue's timeline.
+///
+/// Comments also appear in the timeline, but are fetched separately, so
+/// they fall under `Other` along with any unrecognized events.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum IssueEvent {
+    Labeled {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        label: EventLabel,
+    },
+    Unlabeled {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        label: EventLabel,
+    },
+    Assigned {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        assignee: Option<User>,
+    },
+    Unassigned {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        assignee: Option<User>,
+    },
+    Milestoned {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        milestone: EventMilestone,
+    },
+    Demilestoned {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        milestone: EventMilestone,
+    },
+    Renamed {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        rename: EventRename,
+    },
+    Closed {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        #[serde(default)]
+        commit_id: Option<String>,
+    },
+    Reopened {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+    },
+    Locked {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+    },
+    Unlocked {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+    },
+    /// Referenced from a commit.
+    Referenced {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        #[serde(default)]
+        commit_id: Option<String>,
+        #[serde(default)]
+        commit_url: Option<String>,
+    },
+    /// Referenced from another issue or pull request.
+    CrossReferenced {
+        actor: Option<User>,
+        created_at: DateTime<Utc>,
+        source: EventSource,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl IssueEvent {
+    /// When the event happened, unless unrecognized.
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        match *self {
+            IssueEvent::Labeled { created_at, .. }
+            | IssueEvent::Unlabeled { created_at, .. }
+            | IssueEvent::Assigned { created_at, .. }
+            | IssueEvent::Unassigned { created_at, .. }
+            | IssueEvent::Milestoned { created_at, .. }
+            | IssueEvent::Demilestoned { created_at, .. }
+            | IssueEvent::Renamed { created_at, .. }
+            | IssueEvent::Closed { created_at, .. }
+            | IssueEvent::Reopened { created_at, .. }
+            | IssueEvent::Locked { created_at, .. }
+            | IssueEvent::Unlocked { created_at, .. }
+            | IssueEvent::Referenced { created_at, .. }
+            | IssueEvent::CrossReferenced { created_at, .. } => Some(created_at),
+            IssueEvent::Other => None,
+        }
+    }
+
+    /// Who caused the event, if known.
+    pub fn actor(&self) -> Option<&User> {
+        match *self {
+            IssueEvent::Labeled { ref actor, .. }
+            | IssueEvent::Unlabeled { ref actor, .. }
+            | IssueEvent::Assigned { ref actor, .. }
+            | IssueEvent::Unassigned { ref actor, .. }
+            | IssueEvent::Milestoned { ref actor, .. }
+            | IssueEvent::Demilestoned { ref actor, .. }
+            | IssueEvent::Renamed { ref actor, .. }
+            | IssueEvent::Closed { ref actor, .. }
+            | IssueEvent::Reopened { ref actor, .. }
+            | IssueEvent::Locked { ref actor, .. }
+            | IssueEvent::Unlocked { ref actor, .. }
+            | IssueEvent::Referenced { ref actor, .. }
+            | IssueEvent::CrossReferenced { ref actor, .. } => actor.as_ref(),
+            IssueEvent::Other => None,
+        }
+    }
+}
+
+/// HTTP resource model for the label of a label event.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventLabel {
+    pub name: String,
+    pub color: String,
+}
+
+/// HTTP resource model for the milestone of a milestone event.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventMilestone {
+    pub title: String,
+}
+
+/// HTTP resource model for the titles of a rename event.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// HTTP resource model for the source of a cross reference event.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventSource {
+    #[serde(default)]
+    pub issue: Option<EventSourceIssue>,
+}
+
+/// HTTP resource model for the issue or pull request which cross
+/// referenced another.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventSourceIssue {
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub repository: Option<EventSourceRepo>,
+}
+
+/// HTTP resource model for the repository of a cross referencing issue.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventSourceRepo {
+    pub full_name: String,
+}
+
+/// HTTP resource model for github repository.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Repository {
+    pub id: u64,
+    pub name: String,
+    pub full_name: String,
+    pub owner: User,
+    pub html_url: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub fork: bool,
+    #[serde(default)]
+    pub archived: bool,
+    /// Whether the repository has issues enabled.
+    #[serde(default)]
+    pub has_issues: bool,
 }
 
 /// HTTP resource model for github issue, and its comments.
+///
+/// Timeline events are only present if they were fetched.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IssueWithComments {
     pub issue: Issue,
     pub comments: Vec<Comment>,
+    #[serde(default)]
+    pub events: Vec<IssueEvent>,
+}
+
+/// JSON of a github user, for tests.
+#[cfg(test)]
+pub(crate) const TEST_USER: &'static str = r#"{
+    "login": "octocat", "id": 1, "avatar_url": "", "gravatar_id": null,
+    "url": "", "html_url": "", "followers_url": "", "following_url": "",
+    "gists_url": "", "starred_url": "", "subscriptions_url": "",
+    "organizations_url": "", "repos_url": "", "events_url": "",
+    "received_events_url": "", "type": "User"
+}"#;
+
+/// Issue of the repo `o/r`, for tests.
+#[cfg(test)]
+pub(crate) fn test_issue(number: u64, title: &str, body: &str) -> Issue {
+    let issue = format!(r#"{{
+        "id": {number}, "url": "https://api.github.com/repos/o/r/issues/{number}",
+        "labels_url": "", "comments_url": "", "events_url": "",
+        "html_url": "https://github.com/o/r/issues/{number}", "number": {number},
+        "state": "open", "title": {title}, "body": {body}, "user": {user},
+        "labels": [], "assignee": null, "comments": 0, "closed_at": null,
+        "created_at": "2019-01-01T00:00:00Z",
+        "updated_at": "2019-01-01T00:00:00Z"
+    }}"#,
+        number = number,
+        title = ::serde_json::to_string(title).unwrap(),
+        body = ::serde_json::to_string(body).unwrap(),
+        user = TEST_USER,
+    );
+    ::serde_json::from_str(&issue).unwrap()
+}
+
+/// Timeline of an issue, as github lists it, for tests.
+#[cfg(test)]
+pub(crate) fn test_timeline() -> Vec<IssueEvent> {
+    let timeline = format!(r#"[
+        {{
+            "id": 1, "node_id": "LE_1", "url": "", "actor": {user},
+            "event": "labeled", "commit_id": null, "commit_url": null,
+            "created_at": "2019-01-02T00:00:00Z",
+            "label": {{ "name": "bug", "color": "d73a4a" }}
+        }},
+        {{
+            "id": 2, "node_id": "IC_2", "url": "", "html_url": "",
+            "issue_url": "", "user": {user}, "event": "commented",
+            "body": "a comment", "author_association": "OWNER",
+            "created_at": "2019-01-03T00:00:00Z",
+            "updated_at": "2019-01-03T00:00:00Z"
+        }},
+        {{
+            "actor": {user}, "event": "cross-referenced",
+            "created_at": "2019-01-04T00:00:00Z",
+            "updated_at": "2019-01-04T00:00:00Z",
+            "source": {{
+                "type": "issue",
+                "issue": {{
+                    "id": 7, "number": 7, "title": "other",
+                    "html_url": "https://github.com/o/other/issues/7",
+                    "repository": {{ "id": 3, "full_name": "o/other" }}
+                }}
+            }}
+        }},
+        {{
+            "id": 4, "node_id": "CE_4", "url": "", "actor": {user},
+            "event": "closed", "commit_id": "abc123",
+            "commit_url": "https://api.github.com/repos/o/r/commits/abc123",
+            "created_at": "2019-01-05T00:00:00Z"
+        }},
+        {{
+            "id": 5, "node_id": "X_5", "url": "", "actor": {user},
+            "event": "a-future-event", "created_at": "2019-01-06T00:00:00Z"
+        }}
+    ]"#, user = TEST_USER);
+    ::serde_json::from_str(&timeline).unwrap()
+}
+
+#[test]
+fn deserialize_minimal_issue() {
+    let issue = format!(r#"{{
+        "id": 1, "url": "", "labels_url": "", "comments_url": "",
+        "events_url": "", "html_url": "", "number": 1, "state": "open",
+        "title": "empty", "body": null, "user": {user}, "labels": [],
+        "assignee": null, "comments": 0, "closed_at": null,
+        "created_at": "2019-01-01T00:00:00Z",
+        "updated_at": "2019-01-01T00:00:00Z",
+        "pull_request": {{ "url": "", "html_url": "" }}
+    }}"#, user = TEST_USER);
+
+    let issue: Issue = ::serde_json::from_str(&issue).unwrap();
+    assert_eq!(issue.body, None);
+    assert_eq!(issue.created_at.timestamp(), 1546300800);
+    assert!(issue.is_pull_request());
+}
+
+#[test]
+fn deserialize_timeline() {
+    let timeline = test_timeline();
+    assert_eq!(timeline.len(), 5);
+    match timeline[0] {
+        IssueEvent::Labeled { ref label, ref actor, .. } => {
+            assert_eq!(label.name, "bug");
+            assert_eq!(actor.as_ref().unwrap().login, "octocat");
+        }
+        ref event => panic!("expected labeled, got {:?}", event),
+    }
+    // comments are fetched separately
+    match timeline[1] {
+        IssueEvent::Other => (),
+        ref event => panic!("expected other, got {:?}", event),
+    }
+    match timeline[2] {
+        IssueEvent::CrossReferenced { ref source, .. } => {
+            let issue = source.issue.as_ref().unwrap();
+            assert_eq!(issue.number, 7);
+            assert_eq!(issue.repository.as_ref().unwrap().full_name, "o/other");
+        }
+        ref event => panic!("expected cross-referenced, got {:?}", event),
+    }
+    match timeline[3] {
+        IssueEvent::Closed { ref commit_id, created_at, .. } => {
+            assert_eq!(commit_id.as_ref().unwrap(), "abc123");
+            assert_eq!(created_at.timestamp(), 1546646400);
+        }
+        ref event => panic!("expected closed, got {:?}", event),
+    }
+    match timeline[4] {
+        IssueEvent::Other => (),
+        ref event => panic!("expected other, got {:?}", event),
+    }
 }
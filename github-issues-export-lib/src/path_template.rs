@@ -0,0 +1,342 @@
+
+use crate::{
+    model,
+    error::*,
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+/// Default template of the paths which issues are saved at.
+pub const DEFAULT_PATH_TEMPLATE: &'static str = "{number:03}-{slug}.md";
+
+/// Maximum length of a field's value within a path.
+const MAX_FIELD_LEN: usize = 100;
+
+/// Template of the paths which issues are saved at, relative to the output
+/// directory, such as `{state}/{number:05}-{slug}.md`.
+///
+/// The fields are `{number}`, optionally zero-padded to a width as in
+/// `{number:05}`, `{slug}` and `{title}` of the title, `{state}`, the
+/// first `{label}`, or `unlabeled`, the `{author}`'s login, and the
+/// `{year}` and `{month}` the issue was created in. Field values are
+/// sanitized to be safe as file names, and `/` in the template separates
+/// directories.
+///
+/// The file extension is that of the output format, so a trailing `.md` or
+/// `.html` in the template is replaced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathTemplate {
+    parts: Vec<Part>,
+}
+
+/// Part of a path template.
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Number { width: usize },
+    Slug,
+    Title,
+    State,
+    Label,
+    Author,
+    Year,
+    Month,
+}
+
+impl PathTemplate {
+    /// Path of an issue, with the given file extension.
+    ///
+    /// Issues whose paths collide can be told apart with `paths`.
+    pub fn path(&self, issue: &model::Issue, ext: &str) -> PathBuf {
+        PathBuf::from(format!("{}.{}", self.stem(issue), ext))
+    }
+
+    /// Paths of issues, with the given file extension.
+    ///
+    /// Issues whose paths collide with each other have their number
+    /// appended, except for the lowest numbered one. The `taken` paths of
+    /// other issues, such as those exported previously, are kept, so that
+    /// issues colliding with them have their number appended too.
+    pub fn paths(
+        &self,
+        issues: &[&model::Issue],
+        ext: &str,
+        taken: &BTreeMap<u64, PathBuf>,
+    ) -> Vec<PathBuf> {
+        let stems = issues.iter()
+            .map(|issue| (issue.number, self.stem(issue)))
+            .collect();
+        unique_paths(stems, ext, taken)
+    }
+
+    /// Path of an issue, without the file extension.
+    fn stem(&self, issue: &model::Issue) -> String {
+        let mut stem = String::new();
+        for part in &self.parts {
+            match *part {
+                Part::Literal(ref literal) => stem.push_str(literal),
+                Part::Number { width } => {
+                    write!(stem, "{:0width$}", issue.number, width = width).unwrap();
+                }
+                Part::Slug => {
+                    let slug = slug::slugify(&issue.title);
+                    stem.push_str(&sanitize(if slug.is_empty() { "issue" } else { &slug }));
+                }
+                Part::Title => stem.push_str(&sanitize(&issue.title)),
+                Part::State => stem.push_str(&sanitize(&issue.state)),
+                Part::Label => stem.push_str(&sanitize(issue.labels.first()
+                    .map(|label| label.name.as_str())
+                    .unwrap_or("unlabeled"))),
+                Part::Author => stem.push_str(&sanitize(&issue.user.login)),
+                Part::Year => stem.push_str(&issue.created_at.format("%Y").to_string()),
+                Part::Month => stem.push_str(&issue.created_at.format("%m").to_string()),
+            }
+        }
+        stem
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason: String| -> Error {
+            format!("invalid path template {:?}: {}", s, reason).into()
+        };
+
+        let mut template = s;
+        for ext in &[".md", ".html"] {
+            if template.ends_with(ext) {
+                template = &template[..template.len() - ext.len()];
+            }
+        }
+
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            let open = rest.find('{').unwrap_or(rest.len());
+            if let Some(close) = rest[..open].find('}') {
+                return Err(invalid(format!("unmatched }} at {:?}", &rest[close..])));
+            }
+            if open > 0 {
+                parts.push(Part::Literal(rest[..open].to_owned()));
+            }
+            if open == rest.len() {
+                break;
+            }
+
+            let close = rest[open..].find('}')
+                .map(|close| open + close)
+                .ok_or_else(|| invalid("unmatched {".to_owned()))?;
+            let field = &rest[open + 1..close];
+            let (name, format) = match field.find(':') {
+                Some(i) => (&field[..i], Some(&field[i + 1..])),
+                None => (field, None),
+            };
+            let part = match (name, format) {
+                ("number", None) => Part::Number { width: 0 },
+                ("number", Some(format)) => {
+                    let width = format.trim_start_matches('0').parse::<usize>()
+                        .ok()
+                        .filter(|_| format.starts_with('0'))
+                        .ok_or_else(|| invalid(format!(
+                            "invalid width {:?}, expected a form like {{number:05}}",
+                            format,
+                        )))?;
+                    Part::Number { width }
+                }
+                ("slug", None) => Part::Slug,
+                ("title", None) => Part::Title,
+                ("state", None) => Part::State,
+                ("label", None) => Part::Label,
+                ("author", None) => Part::Author,
+                ("year", None) => Part::Year,
+                ("month", None) => Part::Month,
+                (_, Some(_)) if name != "number" => {
+                    return Err(invalid(format!("only {{number}} has a format, not {{{}}}", name)));
+                }
+                _ => return Err(invalid(format!("unknown field {{{}}}", name))),
+            };
+            parts.push(part);
+            rest = &rest[close + 1..];
+        }
+
+        // paths must stay within the output directory, and name a file
+        let literal: String = parts.iter()
+            .map(|part| match *part {
+                Part::Literal(ref literal) => literal.as_str(),
+                _ => "x",
+            })
+            .collect();
+        let within = Path::new(&literal).components().all(|component| match component {
+            Component::Normal(_) => true,
+            _ => false,
+        });
+        if !within || literal.is_empty() || literal.ends_with('/') {
+            return Err(invalid("expected a relative path to a file".to_owned()));
+        }
+        Ok(PathTemplate { parts })
+    }
+}
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        DEFAULT_PATH_TEMPLATE.parse().expect("invalid default path template")
+    }
+}
+
+/// Paths of issues, given their numbers and path stems, which are unique
+/// among them and the `taken` paths of other issues.
+fn unique_paths(
+    stems: Vec<(u64, String)>,
+    ext: &str,
+    taken: &BTreeMap<u64, PathBuf>,
+) -> Vec<PathBuf> {
+    let numbers: BTreeSet<u64> = stems.iter().map(|&(number, _)| number).collect();
+    let mut used: BTreeSet<PathBuf> = taken.iter()
+        .filter(|&(number, _)| !numbers.contains(number))
+        .map(|(_, path)| path.clone())
+        .collect();
+
+    // lowest number of the issues with each path
+    let mut owners: BTreeMap<PathBuf, u64> = BTreeMap::new();
+    for &(number, ref stem) in &stems {
+        let owner = owners.entry(PathBuf::from(format!("{}.{}", stem, ext)))
+            .or_insert(number);
+        if number < *owner {
+            *owner = number;
+        }
+    }
+
+    // the owners keep their paths, unless taken
+    let mut paths: Vec<Option<PathBuf>> = stems.iter()
+        .map(|&(number, ref stem)| {
+            let path = PathBuf::from(format!("{}.{}", stem, ext));
+            if owners.get(&path) == Some(&number) && !used.contains(&path) {
+                used.insert(path.clone());
+                Some(path)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // the others have their number appended, and a counter if need be
+    for (&(number, ref stem), path) in stems.iter().zip(&mut paths) {
+        if path.is_some() {
+            continue;
+        }
+        let mut candidate = PathBuf::from(format!("{}-{}.{}", stem, number, ext));
+        let mut counter = 2;
+        while used.contains(&candidate) {
+            candidate = PathBuf::from(format!("{}-{}-{}.{}", stem, number, counter, ext));
+            counter += 1;
+        }
+        used.insert(candidate.clone());
+        *path = Some(candidate);
+    }
+    paths.into_iter().map(|path| path.unwrap()).collect()
+}
+
+/// Make a field's value safe as a file name, replacing path separators and
+/// characters which filesystems don't allow.
+fn sanitize(value: &str) -> String {
+    let sanitized: String = value.chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '-' } else { c })
+        .take(MAX_FIELD_LEN)
+        .collect();
+    // no hidden files, `..`, or trailing dots which windows drops
+    let sanitized = sanitized.trim_matches(|c| c == '.' || c == ' ');
+    if sanitized.is_empty() {
+        "_".to_owned()
+    } else {
+        sanitized.to_owned()
+    }
+}
+
+/// Relative link from one exported file to another, given their paths
+/// relative to the output directory.
+pub fn relative_href(from: &Path, to: &Path) -> String {
+    let from_dir: Vec<Component> = from.parent()
+        .map(|dir| dir.components().collect())
+        .unwrap_or_default();
+    let to: Vec<Component> = to.components().collect();
+    let common = from_dir.iter().zip(&to)
+        .take_while(|&(a, b)| a == b)
+        .count();
+
+    let mut href = "../".repeat(from_dir.len() - common);
+    let rest: Vec<String> = to[common..].iter()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    for c in rest.join("/").chars() {
+        // characters which would end or break a markdown link target
+        match c {
+            ' ' => href.push_str("%20"),
+            '#' => href.push_str("%23"),
+            '%' => href.push_str("%25"),
+            '(' => href.push_str("%28"),
+            ')' => href.push_str("%29"),
+            '<' => href.push_str("%3C"),
+            '>' => href.push_str("%3E"),
+            '?' => href.push_str("%3F"),
+            _ => href.push(c),
+        }
+    }
+    href
+}
+
+#[test]
+fn path_template_parse() {
+    assert!("{state}/{number:05}-{slug}.md".parse::<PathTemplate>().is_ok());
+    assert!("{label}/{number}.md".parse::<PathTemplate>().is_ok());
+    for invalid in &["{votes}.md", "{number:5}.md", "{slug:03}", "{number", "a}", "../{slug}", "/{slug}", "{state}/"] {
+        assert!(invalid.parse::<PathTemplate>().is_err(), "{} parsed", invalid);
+    }
+    assert_eq!(sanitize("../a/b: c?"), "-a-b- c-");
+}
+
+#[test]
+fn colliding_paths() {
+    let stems = |stems: &[(u64, &str)]| -> Vec<(u64, String)> {
+        stems.iter().map(|&(number, stem)| (number, stem.to_owned())).collect()
+    };
+    let paths = |paths: Vec<PathBuf>| -> Vec<String> {
+        paths.iter().map(|path| path.to_string_lossy().into_owned()).collect()
+    };
+
+    // the lowest number keeps the path
+    assert_eq!(
+        paths(unique_paths(stems(&[(7, "a"), (2, "a"), (3, "b")]), "md", &BTreeMap::new())),
+        vec!["a-7.md", "a.md", "b.md"],
+    );
+
+    // previously exported issues keep their paths, even if higher numbered
+    let mut taken = BTreeMap::new();
+    taken.insert(5, PathBuf::from("a.md"));
+    taken.insert(3, PathBuf::from("old.md"));
+    assert_eq!(
+        paths(unique_paths(stems(&[(3, "a")]), "md", &taken)),
+        vec!["a-3.md"],
+    );
+
+    // appended numbers don't collide with other paths
+    assert_eq!(
+        paths(unique_paths(stems(&[(1, "a-2"), (2, "a"), (3, "a")]), "md", &taken)),
+        vec!["a-2.md", "a-2-2.md", "a-3.md"],
+    );
+}
+
+#[test]
+fn relative_hrefs() {
+    let href = |from: &str, to: &str| relative_href(Path::new(from), Path::new(to));
+    assert_eq!(href("001-a.md", "002-b.md"), "002-b.md");
+    assert_eq!(href("open/001-a.md", "open/002-b.md"), "002-b.md");
+    assert_eq!(href("open/001-a.md", "closed/003 c#.md"), "../closed/003%20c%23.md");
+    assert_eq!(href("bug/x/1.md", "assets/a.png"), "../../assets/a.png");
+}
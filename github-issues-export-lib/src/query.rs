@@ -0,0 +1,341 @@
+
+use crate::{
+    IssueState,
+    RepoLocation,
+    error::*,
+};
+
+use std::{
+    fmt::{self, Display, Formatter, Write},
+    str::FromStr,
+};
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+/// Filters and ordering for listing the issues of a repo.
+///
+/// Unset filters are left to github's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IssueQuery {
+    pub state: Option<IssueState>,
+    pub labels: Vec<String>,
+    pub milestone: Option<String>,
+    pub assignee: Option<String>,
+    pub creator: Option<String>,
+    pub mentioned: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub sort: Option<IssueSort>,
+    pub direction: Option<SortDirection>,
+}
+
+/// Possible fields to sort issues by.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Deserialize)]
+pub enum IssueSort {
+    Created,
+    Updated,
+    Comments,
+}
+
+/// Possible directions to sort issues in.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Deserialize)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl IssueQuery {
+    pub fn new() -> Self {
+        IssueQuery::default()
+    }
+
+    /// Only issues in this state.
+    pub fn state(mut self, state: IssueState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Only issues with this label. May be called repeatedly, to require
+    /// every label.
+    pub fn label<S: Into<String>>(mut self, label: S) -> Self {
+        self.labels.push(label.into());
+        self
+    }
+
+    /// Only issues in the milestone with this number, or `*` for any
+    /// milestone, or `none` for no milestone.
+    pub fn milestone<S: Into<String>>(mut self, milestone: S) -> Self {
+        self.milestone = Some(milestone.into());
+        self
+    }
+
+    /// Only issues assigned to this login, or `*` for any assignee, or
+    /// `none` for no assignee.
+    pub fn assignee<S: Into<String>>(mut self, assignee: S) -> Self {
+        self.assignee = Some(assignee.into());
+        self
+    }
+
+    /// Only issues created by this login.
+    pub fn creator<S: Into<String>>(mut self, creator: S) -> Self {
+        self.creator = Some(creator.into());
+        self
+    }
+
+    /// Only issues mentioning this login.
+    pub fn mentioned<S: Into<String>>(mut self, mentioned: S) -> Self {
+        self.mentioned = Some(mentioned.into());
+        self
+    }
+
+    /// Only issues updated at or after this time.
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Field to sort issues by.
+    pub fn sort(mut self, sort: IssueSort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Direction to sort issues in.
+    pub fn direction(mut self, direction: SortDirection) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Encode as URL query parameters, without the leading `?`.
+    pub fn to_query_string(&self) -> String {
+        let mut params: Vec<(&str, String)> = Vec::new();
+        if let Some(state) = self.state {
+            params.push(("state", state.to_string()));
+        }
+        if !self.labels.is_empty() {
+            params.push(("labels", self.labels.join(",")));
+        }
+        if let Some(ref milestone) = self.milestone {
+            params.push(("milestone", milestone.clone()));
+        }
+        if let Some(ref assignee) = self.assignee {
+            params.push(("assignee", assignee.clone()));
+        }
+        if let Some(ref creator) = self.creator {
+            params.push(("creator", creator.clone()));
+        }
+        if let Some(ref mentioned) = self.mentioned {
+            params.push(("mentioned", mentioned.clone()));
+        }
+        if let Some(since) = self.since {
+            params.push(("since", since.format("%Y-%m-%dT%H:%M:%SZ").to_string()));
+        }
+        if let Some(sort) = self.sort {
+            params.push(("sort", sort.to_string()));
+        }
+        if let Some(direction) = self.direction {
+            params.push(("direction", direction.to_string()));
+        }
+
+        params.iter()
+            .map(|&(key, ref value)| format!("{}={}", key, percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Encode the filters as URL query parameters, leaving out `since` and
+    /// the ordering, which don't change which issues match.
+    pub fn filter_key(&self) -> String {
+        IssueQuery {
+            since: None,
+            sort: None,
+            direction: None,
+            ..self.clone()
+        }.to_query_string()
+    }
+}
+
+impl From<IssueState> for IssueQuery {
+    fn from(state: IssueState) -> Self {
+        IssueQuery::new().state(state)
+    }
+}
+
+impl Display for IssueSort {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            IssueSort::Created => "created",
+            IssueSort::Updated => "updated",
+            IssueSort::Comments => "comments",
+        })
+    }
+}
+
+impl Display for SortDirection {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        })
+    }
+}
+
+/// What to export, as parsed from `owner/repo`, `owner/repo#selection`
+/// or `owner/*`.
+///
+/// A selection is a comma-separated list of issue numbers and ranges,
+/// such as `#3,7,19`, `#10-25`, or `#100-` for issue 100 onwards.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    /// Issues of a repo, in the given ranges, or all of them if none are
+    /// given.
+    Repo(RepoLocation, Vec<IssueRange>),
+    /// Issues of every repo of a user or organization.
+    Owner(String),
+}
+
+/// Inclusive range of issue numbers, which may be open-ended.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct IssueRange {
+    pub start: u64,
+    /// Last issue number in the range, if bounded.
+    pub end: Option<u64>,
+}
+
+impl IssueRange {
+    /// Range of only the given issue number.
+    pub fn single(number: u64) -> Self {
+        IssueRange {
+            start: number,
+            end: Some(number),
+        }
+    }
+
+    /// Whether the range is of a single issue number.
+    pub fn is_single(&self) -> bool {
+        self.end == Some(self.start)
+    }
+
+    pub fn contains(&self, number: u64) -> bool {
+        number >= self.start && self.end.map(|end| number <= end).unwrap_or(true)
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason: &str| -> Error {
+            format!("invalid query {:?}: {}", s, reason).into()
+        };
+
+        let (repo, selection) = match s.find('#') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let parts: Vec<&str> = repo.split('/').collect();
+        if parts.len() != 2 || parts.iter().any(|part| part.is_empty()) {
+            return Err(invalid("expected owner/repo, owner/repo#issues or owner/*"));
+        }
+
+        if parts[1] == "*" {
+            return match selection {
+                Some(_) => Err(invalid("issues can't be selected from every repo")),
+                None => Ok(Query::Owner(parts[0].to_owned())),
+            };
+        }
+        let ranges = match selection {
+            Some(selection) => parse_selection(selection).map_err(|e| invalid(&e))?,
+            None => Vec::new(),
+        };
+        Ok(Query::Repo(RepoLocation::new(parts[0], parts[1]), ranges))
+    }
+}
+
+/// Parse a comma-separated list of issue numbers and ranges.
+fn parse_selection(s: &str) -> ::std::result::Result<Vec<IssueRange>, String> {
+    fn number(s: &str) -> ::std::result::Result<u64, String> {
+        s.trim().parse::<u64>()
+            .map_err(|_| format!("{:?} is not an issue number", s.trim()))
+    }
+
+    if s.trim().is_empty() {
+        return Err("expected issue numbers after #".to_owned());
+    }
+    s.split(',')
+        .map(|part| match part.find('-') {
+            None => number(part).map(IssueRange::single),
+            Some(i) => {
+                let start = number(&part[..i])?;
+                let end = part[i + 1..].trim();
+                if end.is_empty() {
+                    return Ok(IssueRange { start, end: None });
+                }
+                let end = number(end)?;
+                if end < start {
+                    return Err(format!("range {} ends before it starts", part.trim()));
+                }
+                Ok(IssueRange { start, end: Some(end) })
+            }
+        })
+        .collect()
+}
+
+/// Parse a date given as either RFC 3339, or `YYYY-MM-DD` for midnight UTC.
+pub fn parse_date(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Ok(date.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|e| format!("invalid date {:?}: {}", s, e))?;
+    Ok(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()))
+}
+
+/// Percent-encode a query parameter value.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for &byte in value.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9'
+            | b'-' | b'_' | b'.' | b'~' | b'*' | b',' | b':' => encoded.push(byte as char),
+            _ => write!(encoded, "%{:02X}", byte).unwrap(),
+        }
+    }
+    encoded
+}
+
+#[test]
+fn query_parse() {
+    let repo = RepoLocation::new("owner", "repo");
+    let range = |start, end| IssueRange { start, end };
+    assert_eq!(
+        "owner/repo".parse::<Query>().unwrap(),
+        Query::Repo(repo.clone(), vec![]),
+    );
+    assert_eq!(
+        "owner/repo#3,10-25,100-".parse::<Query>().unwrap(),
+        Query::Repo(repo, vec![
+            IssueRange::single(3),
+            range(10, Some(25)),
+            range(100, None),
+        ]),
+    );
+    assert_eq!("owner/*".parse::<Query>().unwrap(), Query::Owner("owner".to_owned()));
+    for invalid in &["owner", "owner/repo/x", "owner/repo#", "owner/repo#x", "owner/repo#9-2", "owner/*#1"] {
+        assert!(invalid.parse::<Query>().is_err(), "{} parsed", invalid);
+    }
+}
+
+#[test]
+fn issue_query_string() {
+    let query = IssueQuery::new()
+        .state(IssueState::Closed)
+        .label("bug")
+        .label("good first issue")
+        .since(parse_date("2019-03-01").unwrap())
+        .sort(IssueSort::Updated);
+    assert_eq!(
+        query.to_query_string(),
+        "state=closed&labels=bug,good%20first%20issue\
+        &since=2019-03-01T00:00:00Z&sort=updated",
+    );
+}
@@ -0,0 +1,145 @@
+
+use std::{
+    str,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{
+    Headers,
+    StatusCode,
+    header::RetryAfter,
+};
+
+/// Rate limit budget, as last reported by github.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RateLimit {
+    /// Maximum number of requests per window.
+    pub limit: u64,
+    /// Number of requests remaining in the current window.
+    pub remaining: u64,
+    /// When the current window resets.
+    pub reset: SystemTime,
+}
+
+impl RateLimit {
+    /// Parse from the `X-RateLimit-*` headers of a response, if present.
+    pub fn from_headers(headers: &Headers) -> Option<Self> {
+        Some(RateLimit {
+            limit: header_u64(headers, "X-RateLimit-Limit")?,
+            remaining: header_u64(headers, "X-RateLimit-Remaining")?,
+            reset: UNIX_EPOCH + Duration::from_secs(
+                header_u64(headers, "X-RateLimit-Reset")?
+            ),
+        })
+    }
+
+    /// Time until the current window resets, or zero if it already has.
+    pub fn until_reset(&self) -> Duration {
+        self.reset.duration_since(SystemTime::now())
+            .unwrap_or(Duration::from_secs(0))
+    }
+}
+
+/// Policy for waiting on and retrying rate limited requests.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of times to retry a single request.
+    pub max_retries: u32,
+    /// Delay before the first retry, if github doesn't specify one.
+    ///
+    /// Doubles with every subsequent retry.
+    pub base_delay: Duration,
+    /// Longest delay to wait before a retry. Requests which would need
+    /// to wait longer fail instead.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Never retry rate limited requests.
+    pub fn never() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            base_delay: Duration::from_secs(0),
+            max_delay: Duration::from_secs(0),
+        }
+    }
+
+    /// Delay before retrying a rate limited response, or `None` to give up.
+    ///
+    /// `attempt` is the number of retries already made.
+    pub fn retry_delay(&self, attempt: u32, headers: &Headers) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let delay = retry_after(headers)
+            .or_else(|| RateLimit::from_headers(headers)
+                .filter(|budget| budget.remaining == 0)
+                .map(|budget| budget.until_reset() + Duration::from_secs(1)))
+            .unwrap_or_else(|| 2u32.checked_pow(attempt)
+                .and_then(|factor| self.base_delay.checked_mul(factor))
+                .unwrap_or(self.max_delay));
+        if delay <= self.max_delay {
+            Some(delay)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_secs(60),
+            max_delay: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+/// Whether a response was rejected by a primary or secondary rate limit.
+pub fn is_rate_limited(status: StatusCode, headers: &Headers, body: &[u8]) -> bool {
+    match status {
+        StatusCode::TooManyRequests => true,
+        StatusCode::Forbidden => {
+            headers.has::<RetryAfter>()
+                || RateLimit::from_headers(headers)
+                    .map(|budget| budget.remaining == 0)
+                    .unwrap_or(false)
+                || String::from_utf8_lossy(body)
+                    .to_lowercase()
+                    .contains("rate limit")
+        }
+        _ => false,
+    }
+}
+
+/// Delay requested by a `Retry-After` header.
+fn retry_after(headers: &Headers) -> Option<Duration> {
+    match *headers.get::<RetryAfter>()? {
+        RetryAfter::Delay(delay) => Some(delay),
+        RetryAfter::DateTime(date) => Some(SystemTime::from(date)
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::from_secs(0))),
+    }
+}
+
+/// Parse a header with a single integer value.
+fn header_u64(headers: &Headers, name: &str) -> Option<u64> {
+    let raw = headers.get_raw(name)?.one()?;
+    str::from_utf8(raw).ok()?.trim().parse().ok()
+}
+
+#[test]
+fn retry_delay_policy() {
+    let policy = RetryPolicy::default();
+
+    let mut headers = Headers::new();
+    headers.set_raw("Retry-After", "30");
+    assert_eq!(policy.retry_delay(0, &headers), Some(Duration::from_secs(30)));
+    assert_eq!(policy.retry_delay(3, &headers), None);
+
+    let headers = Headers::new();
+    assert_eq!(policy.retry_delay(1, &headers), Some(Duration::from_secs(120)));
+    assert!(is_rate_limited(StatusCode::TooManyRequests, &headers, b""));
+    assert!(!is_rate_limited(StatusCode::Forbidden, &headers, b"{}"));
+}
@@ -0,0 +1,265 @@
+
+/// Rewrite `#123` style issue references in markdown into links, for the
+/// issues which `resolve` gives a link target for.
+///
+/// Code spans and fenced code blocks are left alone, as are references
+/// within the text or target of a markdown link, or within an `<a>`
+/// element.
+pub fn link_issue_refs<F>(md: &str, resolve: F) -> String
+    where
+        F: Fn(u64) -> Option<String>,
+{
+    map_prose(md, |text| link_refs_in(text, &resolve))
+}
+
+/// Rewrite links to issues and pull requests of a repo, such as
+/// `https://github.com/owner/repo/issues/123`, into links to the targets
+/// which `resolve` gives.
+///
+/// `repo_url` is the web URL of the repo, without a trailing `/`. URLs
+/// which are link targets are replaced, and bare URLs become `#123`
+/// links. Fragments, such as of comments, are kept, unless the target
+/// has a fragment of its own. Code spans and fenced code blocks are left
+/// alone.
+pub fn link_issue_urls<F>(md: &str, repo_url: &str, resolve: F) -> String
+    where
+        F: Fn(u64) -> Option<String>,
+{
+    map_prose(md, |text| link_urls_in(text, repo_url, &resolve))
+}
+
+/// Web URL of the repo of an issue or pull request, given its URL.
+pub fn repo_url(html_url: &str) -> Option<&str> {
+    html_url.rfind("/issues/")
+        .or_else(|| html_url.rfind("/pull/"))
+        .map(|i| &html_url[..i])
+}
+
+/// Transform the text of markdown outside of code spans and fenced code
+/// blocks.
+fn map_prose<F>(md: &str, transform: F) -> String
+    where
+        F: Fn(&str) -> String,
+{
+    let mut out = String::with_capacity(md.len());
+    let mut fenced = false;
+
+    for line in md.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        if is_fence {
+            fenced = !fenced;
+        }
+        if fenced || is_fence {
+            out.push_str(line);
+            continue;
+        }
+
+        // odd segments are within code spans
+        for (i, segment) in line.split('`').enumerate() {
+            if i > 0 {
+                out.push('`');
+            }
+            if i % 2 == 0 {
+                out.push_str(&transform(segment));
+            } else {
+                out.push_str(segment);
+            }
+        }
+    }
+    out
+}
+
+/// Link `#123` style references within text.
+fn link_refs_in<F>(text: &str, resolve: &F) -> String
+    where
+        F: Fn(u64) -> Option<String>,
+{
+    let mut out = String::with_capacity(text.len());
+    let mut prev: Option<char> = None;
+    // nesting of [link text], and whether within a (link target) or an
+    // <a> element
+    let mut depth = 0;
+    let mut in_target = false;
+    let mut in_anchor = false;
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth > 0 => {
+                depth -= 1;
+                in_target = depth == 0 && text[i + 1..].starts_with('(');
+            }
+            ')' if in_target => in_target = false,
+            '<' if starts_with_tag(&text[i..], "<a") => in_anchor = true,
+            '<' if starts_with_tag(&text[i..], "</a") => in_anchor = false,
+            _ => (),
+        }
+        let may_start = depth == 0 && !in_target && !in_anchor && match prev {
+            None => true,
+            Some(p) => p.is_whitespace() || p == '(',
+        };
+        if c == '#' && may_start {
+            let digits = leading_digits(&text[i + 1..]);
+            let end = i + 1 + digits.len();
+            let ends_cleanly = text[end..].chars().next()
+                .map(|n| !(n.is_alphanumeric() || n == '_' || n == ']'))
+                .unwrap_or(true);
+            let target = if !digits.is_empty() && ends_cleanly {
+                digits.parse::<u64>().ok().and_then(resolve)
+            } else {
+                None
+            };
+            if let Some(target) = target {
+                out.push_str(&format!("[#{}]({})", digits, target));
+                for _ in 0..digits.len() {
+                    chars.next();
+                }
+                prev = digits.chars().last();
+                continue;
+            }
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    out
+}
+
+/// Link URLs of the issues of a repo within text.
+fn link_urls_in<F>(text: &str, repo_url: &str, resolve: &F) -> String
+    where
+        F: Fn(u64) -> Option<String>,
+{
+    let mut out = String::with_capacity(text.len());
+    if repo_url.is_empty() {
+        out.push_str(text);
+        return out;
+    }
+    let mut copied = 0;
+    let mut from = 0;
+    while let Some(found) = text[from..].find(repo_url) {
+        let start = from + found;
+        let rest = &text[start + repo_url.len()..];
+        from = start + repo_url.len();
+
+        let kind = if rest.starts_with("/issues/") {
+            "/issues/"
+        } else if rest.starts_with("/pull/") {
+            "/pull/"
+        } else {
+            continue;
+        };
+        let digits = leading_digits(&rest[kind.len()..]);
+        let mut end = from + kind.len() + digits.len();
+        let ends_cleanly = text[end..].chars().next()
+            .map(|n| !(n.is_alphanumeric() || n == '_'))
+            .unwrap_or(true);
+        let target = if !digits.is_empty() && ends_cleanly {
+            digits.parse::<u64>().ok().and_then(resolve)
+        } else {
+            None
+        };
+        let target = match target {
+            Some(target) => target,
+            None => continue,
+        };
+
+        // keep fragments, such as of comments, unless the target is an
+        // anchor itself
+        let mut fragment = "";
+        if text[end..].starts_with('#') {
+            let len = text[end..]
+                .find(|c: char| c.is_whitespace() || ")]>\"'".contains(c))
+                .unwrap_or(text.len() - end);
+            if !target.contains('#') {
+                fragment = &text[end..end + len];
+            }
+            end += len;
+        }
+
+        let before = &text[..start];
+        if before.ends_with("](") || before.ends_with("href=\"") {
+            out.push_str(&text[copied..start]);
+            out.push_str(&target);
+            out.push_str(fragment);
+        } else if before.ends_with('<') && text[end..].starts_with('>') {
+            // an autolink can't be relative
+            out.push_str(&text[copied..start - 1]);
+            out.push_str(&format!("[#{}]({}{})", digits, target, fragment));
+            end += 1;
+        } else {
+            out.push_str(&text[copied..start]);
+            out.push_str(&format!("[#{}]({}{})", digits, target, fragment));
+        }
+        copied = end;
+        from = end;
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+/// Whether text starts with an HTML tag of the given name, such as `<a`.
+fn starts_with_tag(text: &str, tag: &str) -> bool {
+    text.get(..tag.len())
+        .map(|start| start.eq_ignore_ascii_case(tag))
+        .unwrap_or(false)
+        && text[tag.len()..].starts_with(|c: char| c == '>' || c.is_whitespace())
+}
+
+/// The ASCII digits at the start of text.
+fn leading_digits(text: &str) -> &str {
+    let len = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    &text[..len]
+}
+
+#[test]
+fn link_refs() {
+    let resolve = |n| match n {
+        1 | 2 => Some(format!("#issue-{}", n)),
+        _ => None,
+    };
+    assert_eq!(
+        link_issue_refs("see #1 and (#2), not #3, `#1`, a#1, #1x or [#2]", resolve),
+        "see [#1](#issue-1) and ([#2](#issue-2)), not #3, `#1`, a#1, #1x or [#2]",
+    );
+    assert_eq!(
+        link_issue_refs("```\n#1\n```\n#1\n", resolve),
+        "```\n#1\n```\n[#1](#issue-1)\n",
+    );
+    assert_eq!(
+        link_issue_refs("[see #1 here](#1) <a href=\"x\">and #2</a> #2", resolve),
+        "[see #1 here](#1) <a href=\"x\">and #2</a> [#2](#issue-2)",
+    );
+}
+
+#[test]
+fn link_urls() {
+    let repo = "https://github.com/o/r";
+    let resolve = |n| match n {
+        42 => Some("042-answer.md".to_owned()),
+        _ => None,
+    };
+    assert_eq!(repo_url("https://github.com/o/r/issues/7"), Some(repo));
+    assert_eq!(
+        link_issue_urls(
+            "see https://github.com/o/r/issues/42, [it](https://github.com/o/r/pull/42#x) \
+            <https://github.com/o/r/issues/42> https://github.com/o/r/issues/43 \
+            https://github.com/o/r/issues/420 `https://github.com/o/r/issues/42`",
+            repo,
+            resolve,
+        ),
+        "see [#42](042-answer.md), [it](042-answer.md#x) \
+        [#42](042-answer.md) https://github.com/o/r/issues/43 \
+        https://github.com/o/r/issues/420 `https://github.com/o/r/issues/42`",
+    );
+
+    // anchors within a document can't have a further fragment
+    assert_eq!(
+        link_issue_urls(
+            "https://github.com/o/r/issues/42#issuecomment-1",
+            repo,
+            |n| Some(format!("#issue-{}", n)),
+        ),
+        "[#42](#issue-42)",
+    );
+}
@@ -2,43 +2,175 @@
 use crate::{
     model,
     error::*,
+    front_matter::FrontMatter,
+    references::{link_issue_refs, link_issue_urls, repo_url},
+    path_template::{PathTemplate, relative_href},
+    html,
 };
 
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use once_cell::sync::OnceCell;
+use chrono::{
+    DateTime,
+    FixedOffset,
+    Utc,
+    format::{Item, StrftimeItems},
+};
+use chrono_tz::Tz;
+use handlebars::{
+    Handlebars,
+    Helper,
+    RenderContext,
+    RenderError,
+};
+
+/// Default format of dates in rendered issues.
+pub const DEFAULT_DATE_FORMAT: &'static str = "%Y-%m-%d %H:%M %:z";
 
-use handlebars::Handlebars;
+/// Time zone to render dates in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DateZone {
+    /// Fixed offset from UTC.
+    Fixed(FixedOffset),
+    /// IANA time zone, such as `Europe/Berlin`, which may observe
+    /// daylight saving time.
+    Named(Tz),
+}
 
 /// Issue to markdown rendering service.
 pub struct IssueRenderer {
     hb: Handlebars,
-}
-
-macro_rules! suggest_path {
-    ($issue:expr, $ext:expr)=>{{
-        fn as_issue_with_comments(
-            issue: &model::IssueWithComments
-        ) -> &model::IssueWithComments {
-            issue
-        }
-        let issue = as_issue_with_comments(&$issue);
-
-        PathBuf::from(format!(
-            concat!("{:03}-{}", $ext),
-            issue.issue.number,
-            slug::slugify(&issue.issue.title),
-        ))
-    }};
+    front_matter: Option<FrontMatter>,
+    path_template: PathTemplate,
+    /// Loaded on first use, as loading the syntax definitions is slow.
+    markdown: OnceCell<html::Markdown>,
 }
 
 impl IssueRenderer {
     pub fn new() -> Self {
+        IssueRenderer::from_template_str(TEMPLATE)
+            .expect("unexpected handlebars template compilation failure")
+    }
+
+    /// Construct with a custom handlebars template, which is compiled
+    /// immediately to report errors early.
+    ///
+    /// The template is rendered with `issue`, `comments` and `events` as
+    /// fetched from github, and `history`, a chronological list of entries
+    /// with either a `comment`, or an `event` with a markdown
+    /// `description`. Besides the builtin helpers, `{{date d}}` formats a
+    /// date, and `{{join list ", " key="name"}}` joins a list.
+    pub fn from_template_str(template: &str) -> Result<Self> {
         let mut reg = Handlebars::new();
-        reg.register_template_string("issue", TEMPLATE)
-            .expect("unexpected handlebars template compilation failure");
+        reg.register_template_string("issue", template)?;
+        reg.register_helper("join", Box::new(join_helper));
 
-        IssueRenderer {
+        let mut renderer = IssueRenderer {
             hb: reg,
+            front_matter: None,
+            path_template: PathTemplate::default(),
+            markdown: OnceCell::new(),
+        };
+        renderer.register_date_helper(
+            DEFAULT_DATE_FORMAT.to_owned(),
+            DateZone::Fixed(FixedOffset::east_opt(0).unwrap()),
+        );
+        Ok(renderer)
+    }
+
+    /// Construct with a custom handlebars template read from a file.
+    ///
+    /// See `from_template_str`.
+    pub fn from_template_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let template = fs::read_to_string(path)
+            .chain_err(|| format!("Could not read template {}", path.display()))?;
+        IssueRenderer::from_template_str(&template)
+            .chain_err(|| format!("Could not compile template {}", path.display()))
+    }
+
+    /// Render dates with a strftime-style format, in the given time zone
+    /// or UTC offset.
+    pub fn with_dates<Z: Into<DateZone>>(mut self, format: &str, zone: Z) -> Result<Self> {
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(format!("invalid date format: {:?}", format).into());
         }
+        self.register_date_helper(format.to_owned(), zone.into());
+        Ok(self)
+    }
+
+    /// Prefix rendered markdown with a front matter block of issue
+    /// metadata, for static site generators.
+    pub fn with_front_matter(mut self, front_matter: FrontMatter) -> Self {
+        self.front_matter = Some(front_matter);
+        self
+    }
+
+    /// Save issues at paths given by a template, rather than the default
+    /// of `{number:03}-{slug}.md`.
+    pub fn with_path_template(mut self, path_template: PathTemplate) -> Self {
+        self.path_template = path_template;
+        self
+    }
+
+    /// Suggested relative file path to save an issue at, with the given
+    /// file extension.
+    pub fn suggest_path(&self, issue: &model::IssueWithComments, ext: &str) -> PathBuf {
+        self.path_template.path(&issue.issue, ext)
+    }
+
+    /// Suggested relative file paths to save issues at, telling apart
+    /// issues whose paths collide with each other, or with the `taken`
+    /// paths of other issues.
+    fn suggest_paths(
+        &self,
+        issues: &[model::IssueWithComments],
+        ext: &str,
+        taken: &BTreeMap<u64, PathBuf>,
+    ) -> Vec<PathBuf> {
+        let issues: Vec<&model::Issue> = issues.iter().map(|issue| &issue.issue).collect();
+        self.path_template.paths(&issues, ext, taken)
+    }
+
+    /// Markdown to HTML converter.
+    fn markdown(&self) -> &html::Markdown {
+        self.markdown.get_or_init(html::Markdown::new)
+    }
+
+    /// Register the `date` helper, which formats a serialized date.
+    fn register_date_helper(&mut self, format: String, zone: DateZone) {
+        self.hb.register_helper("date", Box::new(
+            move |h: &Helper, _: &Handlebars, rc: &mut RenderContext|
+                -> ::std::result::Result<(), RenderError>
+            {
+                let param = h.param(0)
+                    .ok_or_else(|| RenderError::new("date helper requires a param"))?;
+                // absent dates render as nothing
+                let date = match param.value().as_str() {
+                    Some(date) => date,
+                    None => return Ok(()),
+                };
+                let date = DateTime::parse_from_rfc3339(date)
+                    .map_err(|e| RenderError::new(&format!("invalid date: {}", e)))?;
+                let rendered = match zone {
+                    DateZone::Fixed(offset) => date.with_timezone(&offset)
+                        .format(&format)
+                        .to_string(),
+                    DateZone::Named(tz) => date.with_timezone(&tz)
+                        .format(&format)
+                        .to_string(),
+                };
+                rc.writer.write_all(rendered.as_bytes())?;
+                Ok(())
+            }
+        ));
     }
 
     /// Render an issue into markdown.
@@ -48,23 +180,548 @@ impl IssueRenderer {
         &self,
         issue: &model::IssueWithComments,
     ) -> Result<(String, PathBuf)> {
-        let md = self.hb.render("issue", &issue)?;
-        let path = suggest_path!(&issue, ".md");
+        let mut md = self.front_matter_block(&issue.issue)?;
+        md.push_str(&self.hb.render("issue", &IssueContext::new(issue))?);
+        let path = self.suggest_path(issue, "md");
         Ok((md, path))
     }
 
-    /// Render an issue into markdown, then into HTML.
+    /// Render issues into markdown, as with `render_md`, linking
+    /// references between them to their files.
+    ///
+    /// References to the issues in `others`, such as those exported
+    /// previously, are linked to the given relative paths, and references
+    /// to any other issues to github.
+    pub fn render_md_linked(
+        &self,
+        issues: &[model::IssueWithComments],
+        others: &BTreeMap<u64, PathBuf>,
+    ) -> Result<Vec<(String, PathBuf)>> {
+        let issue_paths = self.suggest_paths(issues, "md", others);
+        let mut paths = others.clone();
+        for (issue, path) in issues.iter().zip(&issue_paths) {
+            paths.insert(issue.issue.number, path.clone());
+        }
+
+        issues.iter().zip(issue_paths)
+            .map(|(issue, path)| -> Result<(String, PathBuf)> {
+                let mut md = self.front_matter_block(&issue.issue)?;
+                md.push_str(&self.render_linked(issue, |number| paths.get(&number)
+                    .map(|target| relative_href(&path, target)))?);
+                Ok((md, path))
+            })
+            .collect()
+    }
+
+    /// The front matter block of an issue, if configured.
+    fn front_matter_block(&self, issue: &model::Issue) -> Result<String> {
+        match self.front_matter {
+            Some(front_matter) => front_matter.render(issue),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// Render an issue into markdown, then into a sanitized HTML fragment,
+    /// as github-flavored markdown with highlighted code blocks. See
+    /// `render_html_site` for standalone pages.
     ///
     /// Also produce the suggested relative file path to save at.
     pub fn render_html(
         &self,
         issue: &model::IssueWithComments,
     ) -> Result<(String, PathBuf)> {
-        let md = self.hb.render("issue", &issue)?;
-        let html = markdown::to_html(&md);
-        let path = suggest_path!(&issue, ".html");
+        let md = self.hb.render("issue", &IssueContext::new(issue))?;
+        let html = self.markdown().to_html(&md);
+        let path = self.suggest_path(issue, "html");
         Ok((html, path))
     }
+
+    /// Render issues into a single markdown document, with a table of
+    /// contents, an anchor per issue, and references to the included
+    /// issues linked within the document.
+    pub fn render_combined_md(
+        &self,
+        issues: &[model::IssueWithComments],
+    ) -> Result<String> {
+        let included = issue_numbers(issues);
+
+        let mut md = String::from("# Issues\n\n");
+        for issue in issues {
+            md.push_str(&format!(
+                "- [#{}: {}](#{})\n",
+                issue.issue.number,
+                escape_md(&issue.issue.title),
+                anchor(issue.issue.number),
+            ));
+        }
+        for issue in issues {
+            md.push_str(&format!("\n<a id=\"{}\"></a>\n\n", anchor(issue.issue.number)));
+            md.push_str(&self.render_linked(issue, |number| link_anchor(&included, number))?);
+        }
+        Ok(md)
+    }
+
+    /// Render issues into a single, self-contained HTML document, with a
+    /// table of contents, an anchor per issue, and references to the
+    /// included issues linked within the document.
+    pub fn render_combined_html(
+        &self,
+        issues: &[model::IssueWithComments],
+    ) -> Result<String> {
+        let included = issue_numbers(issues);
+
+        let mut doc = String::from("<h1>Issues</h1>\n<ul>\n");
+        for issue in issues {
+            doc.push_str(&format!(
+                "<li><a href=\"#{}\">#{}: {}</a></li>\n",
+                anchor(issue.issue.number),
+                issue.issue.number,
+                html::escape(&issue.issue.title),
+            ));
+        }
+        doc.push_str("</ul>\n");
+        for issue in issues {
+            let md = self.render_linked(issue, |number| link_anchor(&included, number))?;
+            doc.push_str(&format!(
+                "<section id=\"{}\">\n{}\n</section>\n",
+                anchor(issue.issue.number),
+                self.markdown().to_html(&md),
+            ));
+        }
+        Ok(html::inline_page("Issues", &doc))
+    }
+
+    /// Render issues into standalone HTML pages: a page per issue, with
+    /// navigation to the previous and next issue, an `index.html` listing
+    /// the issues by state and by label, and the stylesheet.
+    ///
+    /// References to the included issues are linked to their pages.
+    /// Produces the contents and relative file path of each file.
+    pub fn render_html_site(
+        &self,
+        issues: &[model::IssueWithComments],
+    ) -> Result<Vec<(String, PathBuf)>> {
+        let issue_paths = self.suggest_paths(issues, "html", &BTreeMap::new());
+        let paths: BTreeMap<u64, PathBuf> = issues.iter()
+            .map(|issue| issue.issue.number)
+            .zip(issue_paths.iter().cloned())
+            .collect();
+
+        let mut files = Vec::with_capacity(issues.len() + 2);
+        for (i, (issue, path)) in issues.iter().zip(issue_paths).enumerate() {
+            let prev = i.checked_sub(1).map(|i| &issues[i]);
+            let next = issues.get(i + 1);
+
+            let md = self.render_linked(issue, |number| paths.get(&number)
+                .map(|target| relative_href(&path, target)))?;
+            let body = format!(
+                "{}\n<p>{}</p>\n<article>\n{}\n</article>\n{}",
+                page_nav(prev, next, &path, &paths, ""),
+                issue_badges(&issue.issue),
+                self.markdown().to_html(&md),
+                page_nav(prev, next, &path, &paths, " class=\"bottom\""),
+            );
+            let title = format!("#{}: {}", issue.issue.number, issue.issue.title);
+            let stylesheet = relative_href(&path, Path::new(html::STYLESHEET_PATH));
+            files.push((html::page(&title, &stylesheet, &body), path));
+        }
+
+        let index = html::page("Issues", html::STYLESHEET_PATH, &index_body(issues, &paths));
+        files.push((index, PathBuf::from(INDEX_PATH)));
+        files.push((html::STYLESHEET.to_owned(), PathBuf::from(html::STYLESHEET_PATH)));
+        Ok(files)
+    }
+
+    /// Render an issue into markdown, linking references to the issues
+    /// which `resolve` gives a link target for, and references to other
+    /// issues of the repo to github.
+    ///
+    /// Both `#123` references and URLs of the repo's issues are linked,
+    /// except URLs of the issue itself, which the template links to.
+    fn render_linked<F>(
+        &self,
+        issue: &model::IssueWithComments,
+        resolve: F,
+    ) -> Result<String>
+        where
+            F: Fn(u64) -> Option<String>,
+    {
+        let md = self.hb.render("issue", &IssueContext::new(issue))?;
+        let repo_url = match repo_url(&issue.issue.html_url) {
+            Some(repo_url) => repo_url,
+            None => return Ok(link_issue_refs(&md, resolve)),
+        };
+
+        let md = link_issue_refs(&md, |number| resolve(number)
+            .or_else(|| Some(format!("{}/issues/{}", repo_url, number))));
+        let own_number = issue.issue.number;
+        Ok(link_issue_urls(&md, repo_url, |number| if number == own_number {
+            None
+        } else {
+            resolve(number)
+        }))
+    }
+}
+
+/// Relative path of the index page of standalone HTML pages.
+pub const INDEX_PATH: &'static str = "index.html";
+
+/// Navigation between the pages of issues, and back to the index, from
+/// the page at `from`.
+fn page_nav(
+    prev: Option<&model::IssueWithComments>,
+    next: Option<&model::IssueWithComments>,
+    from: &Path,
+    paths: &BTreeMap<u64, PathBuf>,
+    attrs: &str,
+) -> String {
+    let link = |issue: Option<&model::IssueWithComments>, before: &str, after: &str| {
+        match issue {
+            Some(issue) => format!(
+                "<a href=\"{}\">{}#{}: {}{}</a>",
+                html::escape(&relative_href(from, &paths[&issue.issue.number])),
+                before,
+                issue.issue.number,
+                html::escape(&issue.issue.title),
+                after,
+            ),
+            None => String::new(),
+        }
+    };
+    format!(
+        "<nav{}>\n<span>{}</span>\n<a href=\"{}\">Index</a>\n<span>{}</span>\n</nav>",
+        attrs,
+        link(prev, "&larr; ", ""),
+        relative_href(from, Path::new(INDEX_PATH)),
+        link(next, "", " &rarr;"),
+    )
+}
+
+/// State and labels of an issue, as HTML badges.
+fn issue_badges(issue: &model::Issue) -> String {
+    let mut badges = format!(
+        "<span class=\"state {0}\">{0}</span>",
+        html::escape(&issue.state),
+    );
+    for label in &issue.labels {
+        badges.push_str(&format!(
+            "<span class=\"label\">{}</span>",
+            html::escape(&label.name),
+        ));
+    }
+    badges
+}
+
+/// Body of the index page, listing issues by state, then by label.
+fn index_body(
+    issues: &[model::IssueWithComments],
+    paths: &BTreeMap<u64, PathBuf>,
+) -> String {
+    fn list(
+        body: &mut String,
+        issues: &[&model::IssueWithComments],
+        paths: &BTreeMap<u64, PathBuf>,
+    ) {
+        body.push_str("<ul class=\"issues\">\n");
+        for issue in issues {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">#{}: {}</a>{}</li>\n",
+                html::escape(&relative_href(Path::new(INDEX_PATH), &paths[&issue.issue.number])),
+                issue.issue.number,
+                html::escape(&issue.issue.title),
+                issue.issue.labels.iter()
+                    .map(|label| format!(
+                        "<span class=\"label\">{}</span>",
+                        html::escape(&label.name),
+                    ))
+                    .collect::<String>(),
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    let mut body = String::from("<h1>Issues</h1>\n");
+    for &state in &["open", "closed"] {
+        let in_state: Vec<_> = issues.iter()
+            .filter(|issue| issue.issue.state == state)
+            .collect();
+        if !in_state.is_empty() {
+            body.push_str(&format!(
+                "<h2>{}{} ({})</h2>\n",
+                state[..1].to_uppercase(),
+                &state[1..],
+                in_state.len(),
+            ));
+            list(&mut body, &in_state, paths);
+        }
+    }
+
+    let mut by_label: BTreeMap<&str, Vec<&model::IssueWithComments>> = BTreeMap::new();
+    for issue in issues {
+        for label in &issue.issue.labels {
+            by_label.entry(label.name.as_str()).or_default().push(issue);
+        }
+    }
+    if !by_label.is_empty() {
+        body.push_str("<h2>By label</h2>\n");
+        for (label, labeled) in &by_label {
+            body.push_str(&format!(
+                "<h3>{} ({})</h3>\n",
+                html::escape(label),
+                labeled.len(),
+            ));
+            list(&mut body, labeled, paths);
+        }
+    }
+    body
+}
+
+/// Anchor of an issue within a combined document.
+fn anchor(number: u64) -> String {
+    format!("issue-{}", number)
+}
+
+/// Link target of an issue within a combined document, if included.
+fn link_anchor(included: &BTreeSet<u64>, number: u64) -> Option<String> {
+    if included.contains(&number) {
+        Some(format!("#{}", anchor(number)))
+    } else {
+        None
+    }
+}
+
+/// Numbers of the given issues.
+fn issue_numbers(issues: &[model::IssueWithComments]) -> BTreeSet<u64> {
+    issues.iter().map(|issue| issue.issue.number).collect()
+}
+
+/// Escape text for use within markdown link text.
+fn escape_md(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The `join` helper, which joins a list with a separator, optionally
+/// taking a field of each element.
+fn join_helper(
+    h: &Helper,
+    _: &Handlebars,
+    rc: &mut RenderContext,
+) -> ::std::result::Result<(), RenderError> {
+    let list = h.param(0)
+        .ok_or_else(|| RenderError::new("join helper requires a param"))?
+        .value();
+    let separator = h.param(1)
+        .and_then(|param| param.value().as_str())
+        .unwrap_or(", ");
+    let key = h.hash_get("key")
+        .and_then(|key| key.value().as_str());
+
+    let items: Vec<String> = list.as_array()
+        .map(|items| items.iter()
+            .filter_map(|item| match key {
+                Some(key) => item.get(key),
+                None => Some(item),
+            })
+            .map(|item| match item.as_str() {
+                Some(s) => s.to_owned(),
+                None => item.to_string(),
+            })
+            .collect())
+        .unwrap_or_default();
+    rc.writer.write_all(items.join(separator).as_bytes())?;
+    Ok(())
+}
+
+/// Data which issue templates are rendered with.
+#[derive(Serialize)]
+struct IssueContext<'a> {
+    issue: &'a model::Issue,
+    comments: &'a [model::Comment],
+    events: &'a [model::IssueEvent],
+    /// Comments and events, in chronological order.
+    history: Vec<HistoryEntry<'a>>,
+}
+
+/// Either a comment or an event, in an issue's history.
+#[derive(Serialize)]
+struct HistoryEntry<'a> {
+    comment: Option<&'a model::Comment>,
+    event: Option<EventEntry<'a>>,
+}
+
+/// An event in an issue's history, with its rendered description.
+#[derive(Serialize)]
+struct EventEntry<'a> {
+    details: &'a model::IssueEvent,
+    created_at: DateTime<Utc>,
+    /// Markdown description of the event.
+    description: String,
+}
+
+impl<'a> IssueContext<'a> {
+    fn new(issue: &'a model::IssueWithComments) -> Self {
+        let mut history: Vec<(DateTime<Utc>, HistoryEntry<'a>)> = Vec::new();
+        for comment in &issue.comments {
+            history.push((comment.created_at, HistoryEntry {
+                comment: Some(comment),
+                event: None,
+            }));
+        }
+        for event in &issue.events {
+            let entry = event.created_at()
+                .and_then(|created_at| describe_event(event)
+                    .map(|description| (created_at, description)));
+            if let Some((created_at, description)) = entry {
+                history.push((created_at, HistoryEntry {
+                    comment: None,
+                    event: Some(EventEntry {
+                        details: event,
+                        created_at,
+                        description,
+                    }),
+                }));
+            }
+        }
+        // stable, so comments come before events at the same time
+        history.sort_by_key(|&(created_at, _)| created_at);
+
+        IssueContext {
+            issue: &issue.issue,
+            comments: &issue.comments,
+            events: &issue.events,
+            history: history.into_iter().map(|(_, entry)| entry).collect(),
+        }
+    }
+}
+
+/// Describe an event in markdown, unless it's unrecognized.
+fn describe_event(event: &model::IssueEvent) -> Option<String> {
+    use crate::model::IssueEvent as E;
+
+    fn user(user: &Option<model::User>) -> String {
+        match *user {
+            Some(ref user) => format!("**{}**", user.login),
+            None => "**ghost**".to_owned(),
+        }
+    }
+
+    fn commit(id: &str) -> String {
+        format!("`{}`", id.get(..7).unwrap_or(id))
+    }
+
+    Some(match *event {
+        E::Labeled { ref actor, ref label, .. } =>
+            format!("{} added the label `{}`", user(actor), label.name),
+        E::Unlabeled { ref actor, ref label, .. } =>
+            format!("{} removed the label `{}`", user(actor), label.name),
+        E::Assigned { ref actor, ref assignee, .. } =>
+            format!("{} assigned {}", user(actor), user(assignee)),
+        E::Unassigned { ref actor, ref assignee, .. } =>
+            format!("{} unassigned {}", user(actor), user(assignee)),
+        E::Milestoned { ref actor, ref milestone, .. } =>
+            format!("{} added this to the milestone **{}**", user(actor), milestone.title),
+        E::Demilestoned { ref actor, ref milestone, .. } =>
+            format!("{} removed this from the milestone **{}**", user(actor), milestone.title),
+        E::Renamed { ref actor, ref rename, .. } =>
+            format!(
+                "{} changed the title from **{}** to **{}**",
+                user(actor), rename.from, rename.to,
+            ),
+        E::Closed { ref actor, commit_id: Some(ref id), .. } =>
+            format!("{} closed this in {}", user(actor), commit(id)),
+        E::Closed { ref actor, .. } =>
+            format!("{} closed this", user(actor)),
+        E::Reopened { ref actor, .. } =>
+            format!("{} reopened this", user(actor)),
+        E::Locked { ref actor, .. } =>
+            format!("{} locked this", user(actor)),
+        E::Unlocked { ref actor, .. } =>
+            format!("{} unlocked this", user(actor)),
+        E::Referenced { ref actor, commit_id: Some(ref id), .. } =>
+            format!("{} referenced this in commit {}", user(actor), commit(id)),
+        E::Referenced { ref actor, .. } =>
+            format!("{} referenced this in a commit", user(actor)),
+        E::CrossReferenced { ref actor, ref source, .. } => match source.issue {
+            Some(ref issue) => format!(
+                "{} mentioned this in [{}#{}: {}]({})",
+                user(actor),
+                issue.repository.as_ref()
+                    .map(|repo| repo.full_name.as_str())
+                    .unwrap_or(""),
+                issue.number,
+                issue.title,
+                issue.html_url,
+            ),
+            None => format!("{} mentioned this elsewhere", user(actor)),
+        },
+        E::Other => return None,
+    })
+}
+
+/// Parse a UTC offset such as `+02:00`, `-0530`, `+01`, or `UTC`.
+pub fn parse_utc_offset(s: &str) -> Result<FixedOffset> {
+    let s = s.trim();
+    if s == "Z" || s.eq_ignore_ascii_case("utc") {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+
+    let invalid = || format!("invalid UTC offset: {:?}", s);
+    let (sign, rest) = match s.chars().next() {
+        Some('+') => (1, &s[1..]),
+        Some('-') => (-1, &s[1..]),
+        _ => return Err(invalid().into()),
+    };
+    let digits: String = rest.chars().filter(|&c| c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid().into());
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (&digits[..2], "0"),
+        4 => (&digits[..2], &digits[2..]),
+        _ => return Err(invalid().into()),
+    };
+    let seconds = hours.parse::<i32>().unwrap() * 3600
+        + minutes.parse::<i32>().unwrap() * 60;
+    FixedOffset::east_opt(sign * seconds)
+        .ok_or_else(|| invalid().into())
+}
+
+impl From<FixedOffset> for DateZone {
+    fn from(offset: FixedOffset) -> Self {
+        DateZone::Fixed(offset)
+    }
+}
+
+impl From<Tz> for DateZone {
+    fn from(tz: Tz) -> Self {
+        DateZone::Named(tz)
+    }
+}
+
+impl FromStr for DateZone {
+    type Err = Error;
+
+    /// Parse an IANA time zone name, such as `Europe/Berlin`, or a UTC
+    /// offset as `parse_utc_offset` does.
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(offset) = parse_utc_offset(s) {
+            return Ok(DateZone::Fixed(offset));
+        }
+        s.trim().parse::<Tz>()
+            .map(DateZone::Named)
+            .map_err(|_| format!(
+                "invalid time zone {:?}, expected a name such as Europe/Berlin, \
+                or a UTC offset such as +02:00",
+                s,
+            ).into())
+    }
 }
 
 /// Handlebars template for rendering issue to markdown.
@@ -76,3 +733,112 @@ fn new_issue_renderer() {
     IssueRenderer::new();
 }
 
+#[test]
+fn utc_offset_parse() {
+    let offset = |seconds| FixedOffset::east_opt(seconds).unwrap();
+    assert_eq!(parse_utc_offset("UTC").unwrap(), offset(0));
+    assert_eq!(parse_utc_offset("+02:00").unwrap(), offset(7200));
+    assert_eq!(parse_utc_offset("-0530").unwrap(), offset(-19800));
+    assert!(parse_utc_offset("02:00").is_err());
+
+    assert_eq!("+02:00".parse::<DateZone>().unwrap(), DateZone::Fixed(offset(7200)));
+    assert_eq!(
+        "Europe/Berlin".parse::<DateZone>().unwrap(),
+        DateZone::Named(::chrono_tz::Europe::Berlin),
+    );
+    assert!("Mars/Olympus".parse::<DateZone>().is_err());
+}
+
+#[test]
+fn dates_in_named_zone() {
+    use chrono::TimeZone;
+
+    // central european summer and winter time
+    let tz: Tz = "Europe/Berlin".parse().unwrap();
+    let summer = Utc.ymd(2020, 7, 1).and_hms(12, 0, 0).with_timezone(&tz);
+    let winter = Utc.ymd(2020, 1, 1).and_hms(12, 0, 0).with_timezone(&tz);
+    assert_eq!(summer.format(DEFAULT_DATE_FORMAT).to_string(), "2020-07-01 14:00 +02:00");
+    assert_eq!(winter.format(DEFAULT_DATE_FORMAT).to_string(), "2020-01-01 13:00 +01:00");
+}
+
+#[test]
+fn invalid_template() {
+    assert!(IssueRenderer::from_template_str("{{#each comments}}").is_err());
+}
+
+#[test]
+fn history_in_order() {
+    let comment = |id: u64, created_at: &str| -> model::Comment {
+        ::serde_json::from_str(&format!(r#"{{
+            "id": {id}, "url": "", "html_url": "", "body": "a comment",
+            "user": {user}, "created_at": "{at}", "updated_at": "{at}"
+        }}"#, id = id, user = model::TEST_USER, at = created_at)).unwrap()
+    };
+    let issue = model::IssueWithComments {
+        issue: model::test_issue(1, "an issue", ""),
+        comments: vec![
+            comment(10, "2019-01-03T00:00:00Z"),
+            comment(11, "2019-01-05T00:00:00Z"),
+        ],
+        events: model::test_timeline(),
+    };
+
+    // unrecognized events are left out, and comments come before events
+    // at the same time
+    let history: Vec<String> = IssueContext::new(&issue).history.iter()
+        .map(|entry| match (entry.comment, entry.event.as_ref()) {
+            (Some(comment), _) => format!("comment {}", comment.id),
+            (None, Some(event)) => event.description.clone(),
+            (None, None) => unreachable!(),
+        })
+        .collect();
+    assert_eq!(history, vec![
+        "**octocat** added the label `bug`".to_owned(),
+        "comment 10".to_owned(),
+        history[2].clone(),
+        "comment 11".to_owned(),
+        "**octocat** closed this in `abc123`".to_owned(),
+    ]);
+    assert!(history[2].contains("o/other#7"), "{}", history[2]);
+}
+
+#[test]
+fn html_site_links() {
+    let issue = |number, title, state: &str, body| {
+        let mut issue = model::test_issue(number, title, body);
+        issue.state = state.to_owned();
+        model::IssueWithComments { issue, comments: Vec::new(), events: Vec::new() }
+    };
+    let issues = vec![
+        issue(1, "First", "open", "see #2"),
+        issue(2, "Second", "closed", "fixed by #1, unlike #3"),
+    ];
+    let render = IssueRenderer::new()
+        .with_path_template("{state}/{number:03}-{slug}".parse().unwrap());
+    let files = render.render_html_site(&issues).unwrap();
+
+    let paths: Vec<String> = files.iter()
+        .map(|&(_, ref path)| path.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(paths, vec!["open/001-first.html", "closed/002-second.html", "index.html", "style.css"]);
+
+    let (first, second, index) = (&files[0].0, &files[1].0, &files[2].0);
+    let article = |page: &str| page.split("<article>").nth(1).unwrap().to_owned();
+    assert!(first.starts_with("<!DOCTYPE html>"));
+
+    // references to included issues link to their pages, others to github
+    assert!(article(first).contains("href=\"../closed/002-second.html\""), "{}", first);
+    assert!(article(second).contains("href=\"../open/001-first.html\""), "{}", second);
+    assert!(article(second).contains("href=\"https://github.com/o/r/issues/3\""), "{}", second);
+
+    // navigation to the neighbouring issues, the index and the stylesheet
+    assert!(first.contains("<a href=\"../index.html\">Index</a>"));
+    assert!(first.contains("<a href=\"../closed/002-second.html\">#2: Second &rarr;</a>"));
+    assert!(!first.contains("&larr;"));
+    assert!(second.contains("<a href=\"../open/001-first.html\">&larr; #1: First</a>"));
+    assert!(second.contains("href=\"../style.css\""));
+
+    assert!(index.contains("<a href=\"open/001-first.html\">#1: First</a>"));
+    assert!(index.contains("<a href=\"closed/002-second.html\">#2: Second</a>"));
+    assert!(index.contains("href=\"style.css\""));
+}
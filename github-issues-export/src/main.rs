@@ -5,23 +5,34 @@ extern crate serde;
 extern crate serde_derive;
 extern crate docopt;
 extern crate futures;
+extern crate similar;
 
 use github_issues_export_lib::{
     prelude::*,
     error::*,
+    render::DateZone,
+    query::parse_date,
+    export_state::ExportState,
+    json,
+    csv_export::CSV_FILE_NAME,
+    assets::{ASSETS_DIR, asset_path, find_asset_urls, rewrite_asset_urls},
+    path_template::relative_href,
 };
 
 use std::{
-    path::PathBuf,
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
     fs::File,
-    io::Write,
+    io::{self, Write},
 };
 
 use futures::{
     prelude::*,
-    future::Either,
+    future::{self, Either},
+    stream,
 };
 use docopt::Docopt;
+use similar::TextDiff;
 
 /// Main function wrapper.
 fn main() {
@@ -33,11 +44,35 @@ fn main() {
         for e in e.iter().skip(1) {
             writeln!(stderr, "Caused by: {}", e).expect(errmsg);
         }
+        if let Some(hint) = hint(e.kind()) {
+            writeln!(stderr, "Hint: {}", hint).expect(errmsg);
+        }
 
         ::std::process::exit(1);
     }
 }
 
+/// Suggest how to fix a failed github request.
+fn hint(kind: &ErrorKind) -> Option<&'static str> {
+    match *kind {
+        ErrorKind::NotFound(_) => Some(
+            "check that the repository or issue exists, and that \
+            GITHUB_TOKEN has access to it"
+        ),
+        ErrorKind::Unauthorized(_) => Some(
+            "GITHUB_TOKEN is invalid or has expired"
+        ),
+        ErrorKind::RateLimited(_) => Some(
+            "the GitHub rate limit was exceeded; try again later, lower \
+            --concurrency, or reuse responses with --cache"
+        ),
+        ErrorKind::Forbidden(_) => Some(
+            "GITHUB_TOKEN lacks the permissions for this request"
+        ),
+        _ => None,
+    }
+}
+
 /// Main function meat.
 fn run() -> Result<()> {
     // parse
@@ -45,50 +80,452 @@ fn run() -> Result<()> {
     let (
         github,
         mut core
-    ) = Github::from_auth(args.env_token.clone())?;
-    let repo = RepoLocation::new(&args.arg_username, &args.arg_repo);
+    ) = {
+        let mut builder = Github::builder()
+            .base_url(args.flag_api_url.as_str())
+            .auth(args.env_token.clone())
+            .concurrency(args.flag_concurrency);
+        if let Some(ref cache_dir) = args.flag_cache {
+            builder = builder.cache_dir(cache_dir);
+        }
+        builder.build_with_core()?
+    };
+
+    // compile the renderer before fetching, to report errors early
+    let render = match args.flag_template {
+        Some(ref template) => IssueRenderer::from_template_file(template)?,
+        None => IssueRenderer::new(),
+    };
+    let mut render = render
+        .with_dates(
+            &args.flag_date_format,
+            args.flag_timezone.parse::<DateZone>()?,
+        )?;
+    if let Some(front_matter) = args.flag_front_matter {
+        render = render.with_front_matter(front_matter);
+    }
+    if let Some(ref path_template) = args.flag_path_template {
+        render = render.with_path_template(path_template.parse()?);
+    }
+
+    // list the repos of owners
+    let mut exports: Vec<(RepoLocation, Vec<IssueRange>)> = Vec::new();
+    for query in &args.queries {
+        match *query {
+            Query::Repo(ref repo, ref ranges) => exports.push((repo.clone(), ranges.clone())),
+            Query::Owner(ref owner) => {
+                let repos = core.run(github.owner_repos(owner))?;
+                for repo in repos.iter().filter(|repo| repo.has_issues) {
+                    exports.push((RepoLocation::from(repo), Vec::new()));
+                }
+            }
+        }
+    }
+
+    // export each repo into its own directory, unless there's only one
+    let nested = exports.len() > 1
+        || args.queries.iter().any(|query| match *query {
+            Query::Owner(_) => true,
+            Query::Repo(..) => false,
+        });
+    if !nested {
+        return match exports.pop() {
+            Some((repo, ranges)) => {
+                let out_dir = PathBuf::from(&args.flag_path);
+                export_repo(&github, &mut core, &render, &args, &repo, &ranges, &out_dir)
+            }
+            None => Ok(()),
+        };
+    }
+    let mut failed = 0;
+    for (repo, ranges) in &exports {
+        let out_dir = PathBuf::from(&args.flag_path).join(&repo.user).join(&repo.repo);
+        let result = export_repo(&github, &mut core, &render, &args, repo, ranges, &out_dir);
+        if let Err(e) = result {
+            eprintln!("Could not export {}: {}", repo, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} repo(s) could not be exported",
+            failed,
+            exports.len(),
+        ).into());
+    }
+    Ok(())
+}
+
+/// Export the issues of a repo, or those in the given ranges, into a
+/// directory.
+fn export_repo(
+    github: &Github,
+    core: &mut TokioCore,
+    render: &IssueRenderer,
+    args: &Args,
+    repo: &RepoLocation,
+    ranges: &[IssueRange],
+    out_dir: &Path,
+) -> Result<()> {
+    // load what previous exports wrote
+    if args.flag_format != Format::Jsonl && !args.flag_dry_run {
+        mkdir(out_dir)?;
+    }
+    let mut state = ExportState::load(out_dir)?;
 
     // fetch issues
-    let issues = match args.arg_issue {
-        Some(issue_number) => Either::A(
-            github.issue(&repo, issue_number)
-                .map(|issue| vec![issue])
-        ),
-        None => Either::B(
-            github.issues(&repo, args.flag_state)
-        ),
+    let mut query = issue_query(args)?;
+    if args.flag_incremental && query.since.is_none() {
+        query.since = state.last_updated(&query);
+    }
+    let keep_going = args.flag_keep_going;
+    let events = args.flag_events;
+
+    // JSON Lines are streamed to stdout as issues are fetched, rather than
+    // saved
+    if args.flag_format == Format::Jsonl {
+        let issues = if ranges.is_empty() {
+            Either::A(github.query_issues_stream(repo, &query))
+        } else {
+            Either::B(github.select_issues(repo, ranges, &query)
+                .map(stream::iter_ok)
+                .flatten_stream())
+        };
+        let stdout = io::stdout();
+        let mut failed = Vec::new();
+        let written = github.issue_details_stream(issues, events)
+            .for_each(|result| match result {
+                Ok(issue) => json::write_json_line(&issue, stdout.lock()),
+                Err(failure) => if keep_going {
+                    failed.push(failure);
+                    Ok(())
+                } else {
+                    Err(failure.error)
+                },
+            });
+        core.run(written)?;
+        return report_failed(&failed);
+    }
+
+    // selections aren't full listings, so they have no listing time
+    let issues = if ranges.is_empty() {
+        Either::A(github.query_issues_dated(repo, &query))
+    } else {
+        Either::B(github.select_issues(repo, ranges, &query)
+            .map(|issues| (issues, None)))
     };
+    // CSV only needs the comment counts, which listing already gives
+    let details = args.flag_format != Format::Csv;
     let issues = issues
-        .and_then(|issue_vec| github.issue_comments(issue_vec));
-    let issues = core.run(issues)?;
+        .and_then(|(issue_vec, listed_at)| {
+            let fetched = if !details {
+                let issues = issue_vec.into_iter()
+                    .map(|issue| gh_model::IssueWithComments {
+                        issue,
+                        comments: Vec::new(),
+                        events: Vec::new(),
+                    })
+                    .collect::<Vec<_>>();
+                Either::A(future::ok((issues, Vec::new())))
+            } else {
+                Either::B(match (keep_going, events) {
+                    (true, false) => Either::A(Either::A(
+                        github.issue_comments_partial(issue_vec)
+                    )),
+                    (true, true) => Either::A(Either::B(
+                        github.issue_history_partial(issue_vec)
+                    )),
+                    (false, false) => Either::B(Either::A(
+                        github.issue_comments(issue_vec)
+                            .map(|issues| (issues, Vec::new()))
+                    )),
+                    (false, true) => Either::B(Either::B(
+                        github.issue_history(issue_vec)
+                            .map(|issues| (issues, Vec::new()))
+                    )),
+                })
+            };
+            fetched.map(move |(issues, failed)| (issues, failed, listed_at))
+        });
+    let (issues, failed, listed_at) = core.run(issues)?;
 
-    // render and save
-    let render = IssueRenderer::new();
+    // render, and localize assets
+    let mut files = render_files(render, &issues, &state, args)?;
+    let assets = if args.flag_assets {
+        localize_assets(github, core, out_dir, &mut files, args.flag_dry_run)?
+    } else {
+        BTreeSet::new()
+    };
+
+    // save
+    for (contents, rel_path) in &files {
+        save_file(&out_dir.join(rel_path), contents, args)?;
+    }
+    let mut manifest: BTreeSet<PathBuf> = files.iter()
+        .map(|(_, rel_path)| rel_path.clone())
+        .chain(assets)
+        .collect();
+
+    // track the file of each issue, for incremental exports
+    let per_issue = args.flag_combined.is_none()
+        && (args.flag_format == Format::Md || args.flag_format == Format::Html);
+    let mut renamed = BTreeSet::new();
+    if per_issue {
+        // the files of issues come first, in order
+        for (issue, (_, rel_path)) in issues.iter().zip(files) {
+            // the title, and thus file name, may have changed since
+            if let Some(old_rel_path) = state.record(&issue.issue, rel_path) {
+                if args.flag_incremental {
+                    delete_file(&out_dir.join(&old_rel_path), args)?;
+                    state.manifest.remove(&old_rel_path);
+                } else {
+                    // left for pruning
+                    state.manifest.insert(old_rel_path.clone());
+                }
+                renamed.insert(old_rel_path);
+            }
+        }
+
+        // don't skip over issues which failed, or which weren't selected,
+        // in the next incremental export
+        if let (true, Some(listed_at)) = (failed.is_empty(), listed_at) {
+            state.advance(&query, listed_at);
+        }
+    }
 
-    mkdir(&args.flag_path)?;
-    for issue in &issues {
-        let (md, path) = render.render_md(issue)?;
-        let path = PathBuf::from(&args.flag_path).join(path);
+    // issues which were updated since the previous incremental export, but
+    // no longer match the filters, such as closed ones, aren't current
+    let mut dropped = BTreeSet::new();
+    if args.flag_incremental && args.flag_prune && ranges.is_empty() {
+        if let Some(since) = query.since {
+            let exported: BTreeSet<u64> = issues.iter()
+                .map(|issue| issue.issue.number)
+                .chain(failed.iter().map(|failure| failure.issue.number))
+                .collect();
+            let updated = IssueQuery::new().state(IssueState::All).since(since);
+            dropped = core.run(github.query_issues(repo, &updated))?
+                .into_iter()
+                .map(|issue| issue.number)
+                .filter(|number| !exported.contains(number))
+                .collect();
+        }
+    }
 
-        let mut f = File::create(&path)?;
-        println!("Writing name {}", path.to_str().unwrap());
-        f.write_all(md.as_bytes())?;
+    // files of issues which weren't fetched this time remain current, when
+    // only some issues were exported, or fetching them failed
+    let partial = args.flag_incremental || !ranges.is_empty();
+    if partial {
+        let dropped_files: BTreeSet<&PathBuf> = dropped.iter()
+            .filter_map(|number| state.files.get(number))
+            .collect();
+        manifest.extend(state.manifest.iter()
+            .chain(state.files.values())
+            .filter(|path| !renamed.contains(*path) && !dropped_files.contains(path))
+            .cloned());
+    }
+    for failure in &failed {
+        if let Some(rel_path) = state.files.get(&failure.issue.number) {
+            manifest.insert(rel_path.clone());
+        }
+    }
+
+    // prune files which previous exports wrote, but which are stale now
+    let stale = state.stale_files(&manifest);
+    if args.flag_prune {
+        for rel_path in &stale {
+            delete_file(&out_dir.join(rel_path), args)?;
+        }
+    } else {
+        manifest.extend(stale);
+    }
+    state.files.retain(|_, rel_path| manifest.contains(rel_path));
+    state.manifest = manifest;
+    if !args.flag_dry_run {
+        state.save(out_dir)?;
     }
 
-    // done
+    report_failed(&failed)
+}
+
+/// Report issues skipped by --keep-going, failing if there are any.
+fn report_failed(failed: &[CommentsFailure]) -> Result<()> {
+    if !failed.is_empty() {
+        for failure in failed {
+            eprintln!(
+                "Skipped issue #{}: {}",
+                failure.issue.number,
+                failure.error,
+            );
+        }
+        return Err(format!(
+            "comments of {} issue(s) could not be fetched",
+            failed.len(),
+        ).into());
+    }
     Ok(())
 }
 
+/// Render the files to export, with their paths relative to the output
+/// directory.
+///
+/// When exporting a file per issue, those come first, in order of the
+/// issues. References to issues which previous exports wrote markdown
+/// files for are linked to those.
+fn render_files(
+    render: &IssueRenderer,
+    issues: &[gh_model::IssueWithComments],
+    state: &ExportState,
+    args: &Args,
+) -> Result<Vec<(String, PathBuf)>> {
+    if let Some(ref file) = args.flag_combined {
+        let path = PathBuf::from(file);
+        let html = args.flag_format == Format::Html
+            || path.extension().map(|ext| ext == "html" || ext == "htm").unwrap_or(false);
+        let doc = if html {
+            render.render_combined_html(issues)?
+        } else {
+            render.render_combined_md(issues)?
+        };
+        return Ok(vec![(doc, path)]);
+    }
+
+    Ok(match args.flag_format {
+        Format::Md => {
+            // previous exports into the directory may have been HTML
+            let previous: BTreeMap<u64, PathBuf> = state.files.iter()
+                .filter(|&(_, path)| path.extension().map(|ext| ext == "md").unwrap_or(false))
+                .map(|(&number, path)| (number, path.clone()))
+                .collect();
+            render.render_md_linked(issues, &previous)?
+        }
+        Format::Html => render.render_html_site(issues)?,
+        Format::Json => vec![(json::to_json(issues)?, PathBuf::from(json::JSON_FILE_NAME))],
+        Format::Csv => {
+            let columns = match args.flag_columns {
+                Some(ref columns) => CsvColumn::parse_list(columns)?,
+                None => CsvColumn::ALL.to_vec(),
+            };
+            let csv = CsvWriter::new()
+                .with_columns(columns)
+                .to_string(issues.iter().map(|issue| &issue.issue))?;
+            vec![(csv, PathBuf::from(CSV_FILE_NAME))]
+        }
+        Format::Jsonl => unreachable!("JSON Lines are streamed to stdout"),
+    })
+}
+
+/// Download the images and attachments which rendered files link to into
+/// the assets directory, and link to them relatively instead.
+///
+/// Assets downloaded by previous exports are reused. Assets which fail to
+/// download are reported, and stay linked to their original URL. In a dry
+/// run, missing assets are reported rather than downloaded.
+///
+/// Produces the relative paths of the assets which are linked to.
+fn localize_assets(
+    github: &Github,
+    core: &mut TokioCore,
+    out_dir: &Path,
+    files: &mut [(String, PathBuf)],
+    dry_run: bool,
+) -> Result<BTreeSet<PathBuf>> {
+    let mut urls: Vec<String> = Vec::new();
+    for (contents, _) in files.iter() {
+        for url in find_asset_urls(contents) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+
+    let (existing, missing): (Vec<String>, Vec<String>) = urls.into_iter()
+        .partition(|url| out_dir.join(asset_path(url)).exists());
+    let mut available: BTreeSet<String> = existing.into_iter().collect();
+    if dry_run {
+        for url in missing {
+            println!("create {}", out_dir.join(asset_path(&url)).display());
+            available.insert(url);
+        }
+        rewrite_assets(files, &available);
+        return Ok(available.iter().map(|url| asset_path(url)).collect());
+    }
+    if !missing.is_empty() {
+        mkdir(out_dir.join(ASSETS_DIR))?;
+    }
+    for (url, result) in core.run(github.download_all(missing))? {
+        match result {
+            Ok(bytes) => {
+                let path = out_dir.join(asset_path(&url));
+                let mut f = File::create(&path)?;
+                println!("Writing name {}", path.to_str().unwrap());
+                f.write_all(&bytes)?;
+                available.insert(url);
+            }
+            Err(e) => eprintln!("Could not download {}: {}", url, e),
+        }
+    }
+
+    rewrite_assets(files, &available);
+    Ok(available.iter().map(|url| asset_path(url)).collect())
+}
+
+/// Link to the available assets relatively in rendered files.
+fn rewrite_assets(files: &mut [(String, PathBuf)], available: &BTreeSet<String>) {
+    for (contents, rel_path) in files.iter_mut() {
+        *contents = rewrite_asset_urls(contents, |url| if available.contains(url) {
+            Some(relative_href(rel_path, &asset_path(url)))
+        } else {
+            None
+        });
+    }
+}
+
+/// Build the issue listing query from CLI arguments.
+fn issue_query(args: &Args) -> Result<IssueQuery> {
+    let mut query = IssueQuery::new().state(args.flag_state);
+    if let Some(ref labels) = args.flag_labels {
+        for label in labels.split(',').filter(|label| !label.is_empty()) {
+            query = query.label(label.trim());
+        }
+    }
+    if let Some(ref milestone) = args.flag_milestone {
+        query = query.milestone(milestone.as_str());
+    }
+    if let Some(ref assignee) = args.flag_assignee {
+        query = query.assignee(assignee.as_str());
+    }
+    if let Some(ref creator) = args.flag_creator {
+        query = query.creator(creator.as_str());
+    }
+    if let Some(ref mentioned) = args.flag_mentioned {
+        query = query.mentioned(mentioned.as_str());
+    }
+    if let Some(ref since) = args.flag_since {
+        query = query.since(parse_date(since)?);
+    }
+    if let Some(sort) = args.flag_sort {
+        query = query.sort(sort);
+    }
+    if let Some(direction) = args.flag_direction {
+        query = query.direction(direction);
+    }
+    Ok(query)
+}
+
 /// CLI usage string.
 const USAGE: &'static str = r#"
-Export issues from GitHub into markdown files.
+Export issues from GitHub into markdown, HTML, JSON or CSV files.
 
 Usage:
-  github-issues-export [options] <query>
+  github-issues-export [options] <query>...
   github-issues-export (-h | --help)
   github-issues-export --version
 
-<query> is of the form: username/repo[#issue_number].
+<query> is of the form: username/repo[#issues], or username/* for all repos
+of a user or organization. #issues selects issue numbers and ranges, such as
+#3,7,19, #10-25, or #100- for issue 100 onwards. Ranges are selected from
+the issues matching the filter options. When exporting more than one repo,
+each is written into a username/repo subdirectory of the output directory.
 
 Environment variables:
   GITHUB_TOKEN      Authorization token for GitHub.
@@ -99,6 +536,83 @@ Options:
   -p --path=<directory>             Output directory [default: ./md].
   -s --state=<open|closed|all>      Fetch issues that are open, closed, or
                                     both [default: open].
+  -l --labels=<labels>              Fetch issues with all of these
+                                    comma-separated labels.
+  --milestone=<number|*|none>       Fetch issues in this milestone.
+  --assignee=<login|*|none>         Fetch issues assigned to this user.
+  --creator=<login>                 Fetch issues created by this user.
+  --mentioned=<login>               Fetch issues mentioning this user.
+  --since=<date>                    Fetch issues updated at or after this
+                                    date, as YYYY-MM-DD or RFC 3339.
+  --sort=<created|updated|comments> Order to fetch issues in.
+  --direction=<asc|desc>            Direction of the order.
+  --api-url=<url>                   Base URL of the GitHub API, such as
+                                    https://github.example.com/api/v3 for
+                                    GitHub Enterprise
+                                    [default: https://api.github.com].
+  -j --concurrency=<n>              Maximum number of requests in flight
+                                    [default: 8].
+  -k --keep-going                   Export the remaining issues when fetching
+                                    the comments of some issues fails.
+  -e --events                       Also export timeline events, such as label
+                                    changes, closes and cross-references.
+  -i --incremental                  Only fetch issues updated since the
+                                    previous export into the directory with
+                                    the same filters.
+  -f --format=<format>              Write markdown files (md), standalone
+                                    HTML pages with an index.html (html), a
+                                    JSON array into issues.json (json), JSON
+                                    Lines to stdout (jsonl), or a CSV table
+                                    of issue metadata into issues.csv (csv)
+                                    [default: md].
+  --columns=<columns>               Comma-separated CSV columns, out of
+                                    number, title, state, author, labels,
+                                    assignee, comments, created, closed and
+                                    url. All of them by default.
+  -c --combined=<file>              Write all issues into this single file
+                                    in the output directory, with a table of
+                                    contents. HTML if it ends in .html or
+                                    .htm, or --format is html, markdown
+                                    otherwise.
+  -n --dry-run                      Report the files which would be created,
+                                    modified or deleted, without writing.
+  -d --diff                         Print a unified diff of the changes to
+                                    each file.
+  --prune                           Delete files which previous exports into
+                                    the directory wrote, but which this one
+                                    doesn't, such as of renamed issues, or of
+                                    issues no longer matching the filters.
+                                    Beware that this includes the files of
+                                    every issue not matching the filters,
+                                    even if exported with other filters.
+                                    With --incremental, issues updated since
+                                    the previous export are checked against
+                                    the filters.
+  -a --assets                       Download images and attachments into an
+                                    assets directory, and link to them
+                                    relatively, for offline archives.
+  --cache=<directory>               Cache responses in this directory, and
+                                    revalidate them on later runs.
+  --path-template=<template>        Save issues at paths of this template
+                                    within the output directory, of the
+                                    fields {number}, or {number:05} padded,
+                                    {slug}, {title}, {state}, {label},
+                                    {author}, {year} and {month}, such as
+                                    {state}/{number:05}-{slug}.md. Issues
+                                    whose paths collide have their number
+                                    appended. {number:03}-{slug}.md by
+                                    default.
+  -t --template=<file>              Render issues with this handlebars
+                                    template instead of the builtin one.
+  --front-matter=<yaml|toml>        Begin each markdown file with a front
+                                    matter block of issue metadata, for
+                                    static site generators.
+  --date-format=<format>            strftime-style format of rendered dates
+                                    [default: %Y-%m-%d %H:%M %:z].
+  --timezone=<zone>                 Render dates in this time zone, either an
+                                    IANA name such as Europe/Berlin, which
+                                    observes daylight saving time, or a fixed
+                                    UTC offset such as +02:00 [default: UTC].
 "#;
 
 /// CLI arguments.
@@ -107,15 +621,47 @@ struct Args {
     flag_version: bool,
     #[serde(skip)]
     env_token: String,
-    arg_query: String,
-    #[serde(skip)]
-    arg_username: String,
+    arg_query: Vec<String>,
     #[serde(skip)]
-    arg_repo: String,
-    #[serde(skip)]
-    arg_issue: Option<usize>,
+    queries: Vec<Query>,
     flag_path: String,
     flag_state: IssueState,
+    flag_labels: Option<String>,
+    flag_milestone: Option<String>,
+    flag_assignee: Option<String>,
+    flag_creator: Option<String>,
+    flag_mentioned: Option<String>,
+    flag_since: Option<String>,
+    flag_sort: Option<IssueSort>,
+    flag_direction: Option<SortDirection>,
+    flag_api_url: String,
+    flag_concurrency: usize,
+    flag_keep_going: bool,
+    flag_events: bool,
+    flag_incremental: bool,
+    flag_format: Format,
+    flag_combined: Option<String>,
+    flag_columns: Option<String>,
+    flag_assets: bool,
+    flag_path_template: Option<String>,
+    flag_dry_run: bool,
+    flag_prune: bool,
+    flag_diff: bool,
+    flag_cache: Option<String>,
+    flag_template: Option<String>,
+    flag_front_matter: Option<FrontMatter>,
+    flag_date_format: String,
+    flag_timezone: String,
+}
+
+/// Output file formats.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+enum Format {
+    Md,
+    Html,
+    Json,
+    Jsonl,
+    Csv,
 }
 
 /// Parse CLI arguments.
@@ -129,42 +675,155 @@ fn parse_args() -> Args {
         std::process::exit(0);
     }
 
+    if args.flag_incremental && args.flag_combined.is_some() {
+        eprintln!("--incremental cannot be used with --combined.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+    if args.flag_incremental && args.flag_format != Format::Md {
+        // the other formats are written as a whole, from every issue
+        eprintln!("--incremental can only be used with --format md.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+    let is_document = args.flag_format == Format::Md || args.flag_format == Format::Html;
+    if !is_document && args.flag_combined.is_some() {
+        eprintln!("--combined can only be used with --format md or html.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+    if !is_document && args.flag_assets {
+        eprintln!("--assets can only be used with --format md or html.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+    if (!is_document || args.flag_combined.is_some()) && args.flag_path_template.is_some() {
+        eprintln!("--path-template can only be used with --format md or html, without --combined.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+    if args.flag_format == Format::Jsonl && (args.flag_dry_run || args.flag_diff || args.flag_prune) {
+        eprintln!("--dry-run, --diff and --prune cannot be used with --format jsonl.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+    if args.flag_format != Format::Md && args.flag_front_matter.is_some() {
+        eprintln!("--front-matter can only be used with --format md.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+    if args.flag_format != Format::Csv && args.flag_columns.is_some() {
+        eprintln!("--columns can only be used with --format csv.\n\n{}", USAGE);
+        std::process::exit(1);
+    }
+
     args.env_token = std::env::var("GITHUB_TOKEN").unwrap_or_else(|_| {
         eprintln!("Missing obligatory environment variable GITHUB_TOKEN");
         std::process::exit(1);
     });
 
-    {
-        let parts: Vec<_> = args.arg_query.split("/").collect();
-        if parts.len() != 2 {
-            eprintln!("Wrong argument: {}.\n\n{}", args.arg_query, USAGE);
-            std::process::exit(1);
-        }
-        args.arg_username = String::from(parts[0]);
-        let parts: Vec<_> = parts[1].split("#").collect();
-        if parts.len() == 1 {
-            args.arg_repo = String::from(parts[0]);
-        } else if parts.len() == 2 {
-            args.arg_repo = String::from(parts[0]);
-            args.arg_issue = match parts[1].parse::<usize>() {
-                Ok(value) => Some(value),
-                Err(_) => {
-                    eprintln!("Wrong argument: {}.\n\n{}", args.arg_query, USAGE);
-                    std::process::exit(1);
-                }
+    for query in &args.arg_query {
+        match query.parse::<Query>() {
+            Ok(query) => args.queries.push(query),
+            Err(e) => {
+                eprintln!("Wrong argument: {}.\n\n{}", e, USAGE);
+                std::process::exit(1);
             }
-        } else {
-            eprintln!("Wrong argument: {}.\n\n{}", args.arg_query, USAGE);
-            std::process::exit(1);
         }
     }
 
     args
 }
 
+/// Save a rendered file, unless its contents are unchanged.
+///
+/// With --diff, the changes are printed as a unified diff. With --dry-run,
+/// the file is reported as created or modified rather than written.
+fn save_file(path: &Path, contents: &str, args: &Args) -> Result<()> {
+    let existing = read_existing(path)?
+        .map(|existing| String::from_utf8_lossy(&existing).into_owned());
+    if existing.as_ref().map(|existing| existing == contents).unwrap_or(false) {
+        return Ok(());
+    }
+    if args.flag_diff {
+        print_diff(path, existing.as_ref().map(String::as_str), contents);
+    }
+    if args.flag_dry_run {
+        let action = if existing.is_some() { "modify" } else { "create" };
+        println!("{} {}", action, path.display());
+        Ok(())
+    } else {
+        write_file(path, contents)
+    }
+}
+
+/// Delete a file which an export previously wrote, if it exists.
+///
+/// With --diff, its removal is printed as a unified diff, or noted if it
+/// isn't text. With --dry-run, the file is reported as deleted rather than
+/// removed.
+fn delete_file(path: &Path, args: &Args) -> Result<()> {
+    let existing = match read_existing(path)? {
+        Some(existing) => existing,
+        None => return Ok(()),
+    };
+    if args.flag_diff {
+        // assets and such may not be text
+        match std::str::from_utf8(&existing) {
+            Ok(existing) => print_diff(path, Some(existing), ""),
+            Err(_) => println!("binary file {} removed", path.display()),
+        }
+    }
+    if args.flag_dry_run {
+        println!("delete {}", path.display());
+        Ok(())
+    } else {
+        remove_file(path)
+    }
+}
+
+/// Contents of a file, if it exists.
+fn read_existing(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::from(err)),
+    }
+}
+
+/// Print a unified diff of a file's contents.
+fn print_diff(path: &Path, old: Option<&str>, new: &str) {
+    let name = path.display().to_string();
+    let old_name = if old.is_some() { name.clone() } else { "/dev/null".to_owned() };
+    let new_name = if new.is_empty() && old.is_some() { "/dev/null".to_owned() } else { name };
+    print!(
+        "{}",
+        TextDiff::from_lines(old.unwrap_or(""), new)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_name, &new_name),
+    );
+}
+
+/// Private helper function.
+fn write_file(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        mkdir(parent)?;
+    }
+    let mut f = File::create(path)?;
+    println!("Writing name {}", path.to_str().unwrap());
+    f.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Private helper function.
+fn remove_file(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => {
+            println!("Removing name {}", path.to_str().unwrap());
+            Ok(())
+        }
+        Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(Error::from(err)),
+    }
+}
+
 /// Private helper function.
-fn mkdir(path: &str) -> Result<()> {
-    if let Err(err) = std::fs::create_dir(path) {
+fn mkdir<P: AsRef<Path>>(path: P) -> Result<()> {
+    if let Err(err) = std::fs::create_dir_all(path) {
         match err.kind() {
             std::io::ErrorKind::AlreadyExists => (),
             _ => {
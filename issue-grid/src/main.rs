@@ -26,17 +26,26 @@ use std::{
     path::PathBuf,
     sync::RwLock,
     ops::{Deref, DerefMut},
+    time::{Duration, Instant},
 };
 
-use github_issues_export_lib::prelude::*;
+use github_issues_export_lib::{
+    prelude::*,
+    error::ErrorKind,
+};
 
 use serde::{
     Serialize,
 };
 use rocket::{
     State,
+    Request,
+    http::Status,
     response::{
+        self,
         Redirect,
+        Responder,
+        Response,
     },
 };
 use rocket_contrib::{
@@ -79,23 +88,79 @@ pub struct Config {
     pub organize: OrganizeScope,
 }
 
+/// Delay before fetching again after a failure other than rate limiting.
+const FETCH_RETRY_DELAY: Duration = Duration::from_secs(10);
+
 /// Convenience wrapper.
-pub struct RepoMutex(RwLock<Repo>);
+///
+/// Holds the error to respond with instead, if fetching failed.
+pub struct RepoMutex(RwLock<Result<Repo, FetchError>>);
 
 impl RepoMutex {
-    pub fn new(data: Repo) -> Self {
+    pub fn new(data: Result<Repo, FetchError>) -> Self {
         RepoMutex(RwLock::new(data))
     }
 
-    pub fn read<'a>(&'a self) -> impl Deref<Target=Repo> + 'a {
+    pub fn read<'a>(&'a self) -> impl Deref<Target=Result<Repo, FetchError>> + 'a {
         self.0.read().unwrap()
     }
 
-    pub fn write<'a>(&'a self) -> impl Deref<Target=Repo> + DerefMut + 'a {
+    pub fn write<'a>(&'a self) -> impl Deref<Target=Result<Repo, FetchError>> + DerefMut + 'a {
         self.0.write().unwrap()
     }
 }
 
+/// Failure to fetch the repo, responded with until fetching is retried.
+#[derive(Copy, Clone, Debug)]
+pub struct FetchError {
+    pub status: Status,
+    /// When to fetch again, rather than respond with this error.
+    pub retry_at: Instant,
+}
+
+impl FetchError {
+    /// Classify a github error, retrying rate limited fetches once the
+    /// rate limit resets.
+    fn new(e: &gh_error::Error, rate_limit: Option<RateLimit>) -> Self {
+        let status = match *e.kind() {
+            ErrorKind::NotFound(_) => Status::NotFound,
+            ErrorKind::Unauthorized(_) => Status::Unauthorized,
+            ErrorKind::RateLimited(_) => Status::ServiceUnavailable,
+            ref kind if kind.api_error().is_some() => Status::BadGateway,
+            _ => Status::InternalServerError,
+        };
+        let delay = match *e.kind() {
+            ErrorKind::RateLimited(_) => rate_limit
+                .map(|budget| budget.until_reset().max(Duration::from_secs(1)))
+                .unwrap_or(FETCH_RETRY_DELAY),
+            _ => FETCH_RETRY_DELAY,
+        };
+        FetchError {
+            status,
+            retry_at: Instant::now() + delay,
+        }
+    }
+
+    /// Whether it's time to fetch again.
+    fn should_retry(&self) -> bool {
+        Instant::now() >= self.retry_at
+    }
+}
+
+impl<'r> Responder<'r> for FetchError {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        let mut response = Response::build();
+        response.status(self.status);
+        if self.status == Status::ServiceUnavailable {
+            // rounded up, so as not to retry before the fetch is
+            let wait = self.retry_at.saturating_duration_since(Instant::now());
+            let secs = wait.as_secs() + if wait.subsec_nanos() > 0 { 1 } else { 0 };
+            response.raw_header("Retry-After", secs.to_string());
+        }
+        response.ok()
+    }
+}
+
 /// Mutable global repo data.
 pub struct Repo {
     pub issues: Vec<model::IssueSummary>,
@@ -104,14 +169,22 @@ pub struct Repo {
 
 impl Repo {
     /// This is a blocking function.
-    pub fn fetch(config: &Config) -> Result<Self, ()> {
+    ///
+    /// Fails with the error to respond with.
+    pub fn fetch(config: &Config) -> Result<Self, FetchError> {
         // fetch
         let (github, mut core) = Github::from_auth(config.auth.clone())
-            .unwrap();
+            .map_err(|e| {
+                eprintln!("[error] cannot create github client: {}", e);
+                FetchError::new(&e, None)
+            })?;
         let issues = github
             .issues(&config.repo, IssueState::Open);
         let issues = core.run(issues)
-            .unwrap();
+            .map_err(|e| {
+                eprintln!("[error] cannot fetch issues of {:?}: {}", config.repo, e);
+                FetchError::new(&e, github.rate_limit())
+            })?;
 
         // remodel
         let issues: Vec<model::IssueSummary> = issues.gh_into();
@@ -135,10 +208,26 @@ impl Repo {
 }
 
 #[get("/api/list_issues")]
-fn list_issues(repo_lock: State<RepoMutex>) -> Resp<Vec<model::IssueSummary>> {
+fn list_issues(
+    config: State<Config>,
+    repo_lock: State<RepoMutex>,
+) -> Result<Resp<Vec<model::IssueSummary>>, FetchError> {
+    // fetch again after failures, once it's time to retry
+    let retry = repo_lock.read().as_ref().err()
+        .map(FetchError::should_retry)
+        .unwrap_or(false);
+    if retry {
+        let mut repo = repo_lock.write();
+        // unless another request already did
+        if repo.as_ref().err().map(FetchError::should_retry).unwrap_or(false) {
+            *repo = Repo::fetch(&config);
+        }
+    }
+
     let repo = repo_lock.read();
+    let repo = repo.as_ref().map_err(|&e| e)?;
 
-    resp(repo.issues.clone())
+    Ok(resp(repo.issues.clone()))
 }
 
 /*
@@ -157,8 +246,8 @@ fn main() {
     let config = read_config(base.join("config.yaml"))
         .expect("TODO");
 
-    let repo = Repo::fetch(&config)
-        .expect("TODO");
+    // failures are served as error statuses, until retried
+    let repo = Repo::fetch(&config);
     let repo_lock = RepoMutex::new(repo);
 
     rocket::ignite()